@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{overall_winner, GameStatus, RoundReason, Table, Winner};
+
+const WIN_STREAK: u32 = 5;
+const LONG_HAND_CARDS: usize = 6;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Achievement {
+    FirstBlackjack,
+    FiveInARow,
+    SixCardHand,
+    DoubleBankroll
+}
+
+impl Achievement {
+    pub fn iterator() -> impl Iterator<Item = Achievement> {
+        return [
+            Achievement::FirstBlackjack,
+            Achievement::FiveInARow,
+            Achievement::SixCardHand,
+            Achievement::DoubleBankroll,
+        ].iter().copied();
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Achievement::FirstBlackjack => "First Blackjack",
+            Achievement::FiveInARow => "Win 5 in a row",
+            Achievement::SixCardHand => "Survive a 6-card hand",
+            Achievement::DoubleBankroll => "Double bankroll",
+        };
+    }
+}
+
+/// What the achievements have been building up to this session.
+#[derive(Default)]
+pub struct AchievementProgress {
+    pub win_streak: u32
+}
+
+impl AchievementProgress {
+    /// Every achievement the round that just finished on `table` earns. A
+    /// push leaves the winning streak as it was.
+    pub fn record_round(&mut self, table: &Table, starting_bankroll: u32) -> Vec<Achievement> {
+        let results = match &table.status {
+            GameStatus::GameOver(results) => results,
+            _ => return Vec::<Achievement>::new(),
+        };
+
+        match overall_winner(results) {
+            Winner::Player => self.win_streak += 1,
+            Winner::Casino => self.win_streak = 0,
+            Winner::Tie => {}
+        }
+
+        let mut earned = Vec::<Achievement>::new();
+        if results.iter().any(|result| result.reason == RoundReason::PlayerBlackjack) {
+            earned.push(Achievement::FirstBlackjack);
+        }
+        if self.win_streak >= WIN_STREAK {
+            earned.push(Achievement::FiveInARow);
+        }
+        let survived_long_hand = table.player_hands.iter().any(|hand| {
+            return hand.cards.len() >= LONG_HAND_CARDS && !hand.cards.is_bust();
+        });
+        if survived_long_hand {
+            earned.push(Achievement::SixCardHand);
+        }
+        if table.bankrolls.iter().any(|bankroll| *bankroll >= starting_bankroll * 2) {
+            earned.push(Achievement::DoubleBankroll);
+        }
+
+        return earned;
+    }
+}
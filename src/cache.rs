@@ -0,0 +1,128 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+struct CacheEntry<V> {
+    value: V,
+    last_used: u64,
+    pinned: bool
+}
+
+/// String-keyed cache that drops the least recently used entry once more
+/// than `capacity` unpinned entries are held. Pinned entries never count
+/// towards the capacity and are never evicted.
+pub struct LruCache<V> {
+    entries: HashMap<String, CacheEntry<V>>,
+    capacity: Option<usize>,
+    clock: u64
+}
+
+impl<V> LruCache<V> {
+    /// `None` keeps every entry forever.
+    pub fn new(capacity: Option<usize>) -> LruCache<V> {
+        return LruCache {
+            entries: HashMap::<String, CacheEntry<V>>::new(),
+            capacity: capacity,
+            clock: 0
+        };
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        return self.entries.contains_key(key);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// Looks up `key`, marking it as just used.
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+
+        return Some(&entry.value);
+    }
+
+    pub fn insert(&mut self, key: &str, value: V, pinned: bool) {
+        self.clock += 1;
+        self.entries.insert(key.to_string(), CacheEntry { value: value, last_used: self.clock, pinned: pinned });
+        self.evict();
+    }
+
+    /// Looks up `key` with a single hash, creating the value with `make` when
+    /// it is missing. A failed `make` leaves the cache as it was.
+    pub fn get_or_insert_with<E, F: FnOnce() -> Result<V, E>>(&mut self, key: &str, pinned: bool, make: F) -> Result<V, E>
+    where V: Clone {
+        self.clock += 1;
+        let value = match self.entries.entry(key.to_string()) {
+            Entry::Occupied(entry) => {
+                let entry = entry.into_mut();
+                entry.last_used = self.clock;
+                return Ok(entry.value.clone());
+            },
+            Entry::Vacant(entry) => entry.insert(CacheEntry { value: make()?, last_used: self.clock, pinned: pinned }).value.clone(),
+        };
+        self.evict();
+
+        return Ok(value);
+    }
+
+    fn evict(&mut self) {
+        let capacity = match self.capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+
+        while self.entries.values().filter(|entry| !entry.pinned).count() > capacity {
+            let oldest = self.entries.iter()
+                .filter(|(_, entry)| !entry.pinned)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+                .unwrap();
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_evicts_least_recently_used_but_keeps_pinned() {
+        let mut cache = LruCache::new(Some(2));
+        cache.insert("card", 0, true);
+        cache.insert("Bankroll: 10", 1, false);
+        cache.insert("Bankroll: 20", 2, false);
+        cache.get("Bankroll: 10");
+        cache.insert("Bankroll: 30", 3, false);
+        cache.insert("Bankroll: 40", 4, false);
+
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get("card"), Some(&0));
+        assert!(!cache.contains_key("Bankroll: 10"));
+        assert!(!cache.contains_key("Bankroll: 20"));
+        assert_eq!(cache.get("Bankroll: 40"), Some(&4));
+    }
+
+    #[test]
+    fn cache_only_makes_missing_values() {
+        let mut cache = LruCache::new(None);
+        let mut made = 0;
+        for _ in 0..3 {
+            let value = cache.get_or_insert_with("card", true, || -> Result<u32, String> {
+                made += 1;
+                return Ok(7);
+            });
+            assert_eq!(value, Ok(7));
+        }
+        assert_eq!(made, 1);
+
+        assert!(cache.get_or_insert_with("missing", true, || Err("no such file".to_string())).is_err());
+        assert!(!cache.contains_key("missing"));
+    }
+}
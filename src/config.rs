@@ -0,0 +1,446 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const CONFIG_PATH: &str = "config.toml";
+
+const MIN_WINDOW_WIDTH: u32 = 640;
+const MIN_WINDOW_HEIGHT: u32 = 700;
+pub const MAX_DECKS: usize = 8;
+const MAX_PLAYERS: usize = 4;
+pub const MAX_SPLIT_HANDS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool
+}
+
+impl Default for WindowConfig {
+    fn default() -> WindowConfig {
+        return WindowConfig {
+            width: 1200,
+            height: 1000,
+            fullscreen: false
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RulesConfig {
+    pub decks: usize,
+    /// Players seated at the table, each playing their hand in turn.
+    pub players: usize,
+    pub dealer_stop_score: usize,
+    pub dealer_hits_soft_17: bool,
+    pub dealer_wins_ties: bool,
+    /// A player hand of five cards that hasn't busted wins outright.
+    pub five_card_charlie: bool,
+    /// What a winning natural pays per unit bet, as (numerator, denominator).
+    pub blackjack_payout: (u32, u32),
+    /// Late surrender, once the dealer has checked for a natural.
+    pub surrender_allowed: bool,
+    /// Surrender against an ace or ten before the dealer checks for a
+    /// natural. Can't be combined with late surrender.
+    pub early_surrender: bool,
+    /// Most hands a player can split into in one round; 1 turns splitting off.
+    pub max_split_hands: usize,
+    pub resplit_aces: bool,
+    /// Split aces get a single card each and stand.
+    pub split_aces_one_card: bool,
+    pub dealing: Dealing,
+    /// The dealer checks the hole card under an ace or ten and ends the
+    /// round on a natural before anyone acts.
+    pub dealer_peeks: bool
+}
+
+/// How the dealer's opening hand is dealt.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Dealing {
+    /// Two cards, the second face down and checked for a natural.
+    American,
+    /// A single card; the second is only drawn once the players are done,
+    /// so a dealer natural takes doubles and splits with it.
+    European
+}
+
+impl Default for RulesConfig {
+    fn default() -> RulesConfig {
+        return RulesConfig {
+            decks: 1,
+            players: 1,
+            dealer_stop_score: 17,
+            dealer_hits_soft_17: false,
+            dealer_wins_ties: false,
+            five_card_charlie: false,
+            blackjack_payout: (3, 2),
+            surrender_allowed: true,
+            early_surrender: false,
+            max_split_hands: 2,
+            resplit_aces: false,
+            split_aces_one_card: true,
+            dealing: Dealing::American,
+            dealer_peeks: true
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BettingConfig {
+    pub starting_bankroll: u32,
+    pub bet_step: u32,
+    /// Table limits every bet has to stay within.
+    pub min_bet: u32,
+    pub max_bet: u32
+}
+
+impl Default for BettingConfig {
+    fn default() -> BettingConfig {
+        return BettingConfig {
+            starting_bankroll: 1000,
+            bet_step: 10,
+            min_bet: 10,
+            max_bet: 500
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Fills the table where there's no felt image.
+    pub table_color: (u8, u8, u8),
+    /// Image stretched over the whole table; empty for the plain colour.
+    pub felt_path: String,
+    /// Folder of card images to use in place of the default set. Any card
+    /// missing from it is drawn from the default set instead.
+    pub cards_dir: String,
+    pub text_color: (u8, u8, u8)
+}
+
+impl Default for ThemeConfig {
+    fn default() -> ThemeConfig {
+        return ThemeConfig {
+            table_color: (25, 120, 50),
+            felt_path: "assets/table/felt.png".to_string(),
+            cards_dir: crate::CARDS_DIR.to_string(),
+            text_color: (255, 255, 255)
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OutcomeStyle {
+    Bottom,
+    Center
+}
+
+/// What Escape does at the table; in the menus it always backs out.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EscapeAction {
+    Quit,
+    /// Opens the pause menu; older configs call this `confirm`.
+    #[serde(alias = "confirm")]
+    Pause
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UiConfig {
+    pub outcome_style: OutcomeStyle,
+    pub outcome_banner_height: u32,
+    pub explain_ties: bool,
+    pub dealer_quips: bool,
+    pub quips_path: String,
+    pub safe_hit_cue: bool,
+    /// Marks every face-up card with a letter for its suit.
+    pub colorblind_mode: bool,
+    pub auto_hit_safe_hands: bool,
+    pub escape_action: EscapeAction,
+    pub mute: bool,
+    /// Most card backs stacked on the discard pile; 0 hides the pile.
+    pub discard_slots: u32,
+    /// A hand that busts flashes red for a moment.
+    pub bust_flash: bool
+}
+
+impl Default for UiConfig {
+    fn default() -> UiConfig {
+        return UiConfig {
+            outcome_style: OutcomeStyle::Bottom,
+            outcome_banner_height: 200,
+            explain_ties: true,
+            dealer_quips: false,
+            quips_path: "assets/quips.toml".to_string(),
+            safe_hit_cue: false,
+            colorblind_mode: false,
+            auto_hit_safe_hands: false,
+            escape_action: EscapeAction::Pause,
+            mute: false,
+            discard_slots: 8,
+            bust_flash: true
+        };
+    }
+}
+
+/// Keys the game always listens for, by SDL name, with what they do.
+const FIXED_KEYS: [(&str, &str); 15] = [
+    ("Escape", "pause"),
+    ("Return", "confirm"),
+    ("Space", "replay step"),
+    ("Up", "menu up"),
+    ("Down", "menu down"),
+    ("Left", "menu left"),
+    ("Right", "menu right"),
+    ("C", "show count"),
+    ("V", "mute"),
+    ("T", "show history"),
+    ("A", "demo"),
+    ("F3", "diagnostics"),
+    ("R", "rebet"),
+    ("M", "menu"),
+    ("Q", "quit"),
+];
+
+/// Keys the game listens for only while a round is being played, which is
+/// when every binding but `restart` is used.
+const ROUND_KEYS: [(&str, &str); 7] = [
+    ("S", "surrender"),
+    ("I", "insurance"),
+    ("Y", "even money"),
+    ("N", "no even money"),
+    ("H", "hints"),
+    ("F5", "redeal"),
+    ("F6", "redeal"),
+];
+
+/// The key for each action at the table, by its SDL name.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub hit: String,
+    pub stand: String,
+    pub double: String,
+    pub split: String,
+    pub restart: String
+}
+
+impl KeysConfig {
+    /// Each action's config key alongside the key bound to it.
+    pub fn bindings(&self) -> [(&'static str, &str); 5] {
+        return [
+            ("hit", &self.hit),
+            ("stand", &self.stand),
+            ("double", &self.double),
+            ("split", &self.split),
+            ("restart", &self.restart),
+        ];
+    }
+}
+
+impl Default for KeysConfig {
+    fn default() -> KeysConfig {
+        return KeysConfig {
+            hit: "F".to_string(),
+            stand: "E".to_string(),
+            double: "D".to_string(),
+            split: "P".to_string(),
+            restart: "N".to_string()
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub rules: RulesConfig,
+    pub betting: BettingConfig,
+    pub theme: ThemeConfig,
+    pub ui: UiConfig,
+    pub keys: KeysConfig
+}
+
+impl Config {
+    /// Reads the config file at `path`. A missing file yields the defaults,
+    /// missing keys fall back to their default values and unknown keys are
+    /// logged as warnings but otherwise ignored.
+    pub fn load(path: &str) -> Result<Config, String> {
+        if !Path::new(path).exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read config file {}: {}", path, err))?;
+
+        return Config::parse(&content)
+            .map_err(|err| format!("invalid config file {}: {}", path, err));
+    }
+
+    pub fn parse(content: &str) -> Result<Config, String> {
+        let table = content.parse::<toml::Table>().map_err(|err| err.message().to_string())?;
+
+        let known = toml::Table::try_from(Config::default()).unwrap();
+        for key in find_unknown_keys(&table, &known, "") {
+            warn!("unknown config key `{}` ignored", key);
+        }
+
+        let config: Config = table.try_into().map_err(|err: toml::de::Error| err.message().to_string())?;
+        config.validate()?;
+
+        return Ok(config);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.window.width < MIN_WINDOW_WIDTH {
+            return Err(format!("window.width must be at least {}, got {}", MIN_WINDOW_WIDTH, self.window.width));
+        }
+
+        if self.window.height < MIN_WINDOW_HEIGHT {
+            return Err(format!("window.height must be at least {}, got {}", MIN_WINDOW_HEIGHT, self.window.height));
+        }
+
+        if self.rules.decks < 1 || self.rules.decks > MAX_DECKS {
+            return Err(format!("rules.decks must be between 1 and {}, got {}", MAX_DECKS, self.rules.decks));
+        }
+
+        if self.rules.players < 1 || self.rules.players > MAX_PLAYERS {
+            return Err(format!("rules.players must be between 1 and {}, got {}", MAX_PLAYERS, self.rules.players));
+        }
+
+        if self.rules.dealer_stop_score < 2 || self.rules.dealer_stop_score > 21 {
+            return Err(format!("rules.dealer_stop_score must be between 2 and 21, got {}", self.rules.dealer_stop_score));
+        }
+
+        if self.rules.max_split_hands < 1 || self.rules.max_split_hands > MAX_SPLIT_HANDS {
+            return Err(format!("rules.max_split_hands must be between 1 and {}, got {}", MAX_SPLIT_HANDS, self.rules.max_split_hands));
+        }
+
+        if self.rules.surrender_allowed && self.rules.early_surrender {
+            return Err("rules.early_surrender needs rules.surrender_allowed set to false".to_string());
+        }
+
+        if self.rules.blackjack_payout.1 == 0 {
+            return Err("rules.blackjack_payout denominator must be greater than 0".to_string());
+        }
+
+        if self.betting.bet_step == 0 {
+            return Err("betting.bet_step must be greater than 0".to_string());
+        }
+
+        if self.betting.starting_bankroll < self.betting.bet_step {
+            return Err(format!("betting.starting_bankroll must be at least betting.bet_step ({}), got {}",
+                self.betting.bet_step, self.betting.starting_bankroll));
+        }
+
+        if self.betting.min_bet == 0 {
+            return Err("betting.min_bet must be greater than 0".to_string());
+        }
+
+        if self.betting.max_bet < self.betting.min_bet {
+            return Err(format!("betting.max_bet must be at least betting.min_bet ({}), got {}",
+                self.betting.min_bet, self.betting.max_bet));
+        }
+
+        if self.betting.starting_bankroll < self.betting.min_bet {
+            return Err(format!("betting.starting_bankroll must be at least betting.min_bet ({}), got {}",
+                self.betting.min_bet, self.betting.starting_bankroll));
+        }
+
+        if self.ui.outcome_banner_height == 0 || self.ui.outcome_banner_height > self.window.height {
+            return Err(format!("ui.outcome_banner_height must be between 1 and window.height ({}), got {}",
+                self.window.height, self.ui.outcome_banner_height));
+        }
+
+        let bindings = self.keys.bindings();
+        for (idx, (action, key)) in bindings.iter().enumerate() {
+            for (other_action, other_key) in &bindings[idx + 1..] {
+                if key.eq_ignore_ascii_case(other_key) {
+                    return Err(format!("keys.{} and keys.{} are both bound to {}", action, other_action, key));
+                }
+            }
+
+            let round_keys: &[(&str, &str)] = if *action == "restart" { &[] } else { &ROUND_KEYS };
+            for (fixed_key, fixed_action) in FIXED_KEYS.iter().chain(round_keys) {
+                if key.eq_ignore_ascii_case(fixed_key) {
+                    return Err(format!("keys.{} and {} are both bound to {}", action, fixed_action, fixed_key));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+/// Writes what the settings screen changes, `config`'s rules, colorblind
+/// mode and bust flash, into the config file at `path`, leaving everything else as it is.
+pub fn save_settings(path: &str, config: &Config) -> Result<(), String> {
+    let mut table = if Path::new(path).exists() {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read config file {}: {}", path, err))?;
+        content.parse::<toml::Table>().map_err(|err| format!("invalid config file {}: {}", path, err.message()))?
+    } else {
+        toml::Table::new()
+    };
+
+    let rules = toml::Value::try_from(&config.rules).map_err(|err| format!("failed to write config file {}: {}", path, err))?;
+    table.insert("rules".to_string(), rules);
+    let ui = table.entry("ui").or_insert(toml::Value::Table(toml::Table::new()));
+    if let toml::Value::Table(ui) = ui {
+        ui.insert("colorblind_mode".to_string(), toml::Value::Boolean(config.ui.colorblind_mode));
+        ui.insert("bust_flash".to_string(), toml::Value::Boolean(config.ui.bust_flash));
+    }
+    let content = toml::to_string(&table).map_err(|err| format!("failed to write config file {}: {}", path, err))?;
+
+    return fs::write(path, content)
+        .map_err(|err| format!("failed to write config file {}: {}", path, err));
+}
+
+fn find_unknown_keys(table: &toml::Table, known: &toml::Table, prefix: &str) -> Vec<String> {
+    let mut unknown = Vec::<String>::new();
+    for (key, value) in table {
+        let name = prefix.to_owned() + key;
+        match (known.get(key), value) {
+            (None, _) => unknown.push(name),
+            (Some(toml::Value::Table(known_section)), toml::Value::Table(section)) => {
+                unknown.extend(find_unknown_keys(section, known_section, &(name + ".")));
+            },
+            _ => {}
+        }
+    }
+
+    return unknown;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_settings_keep_the_rest_of_the_config() {
+        let path = std::env::temp_dir().join("blackjack_save_rules_test.toml");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "[window]\nwidth = 800\n\n[rules]\ndecks = 2\n").unwrap();
+
+        let settings = Config {
+            rules: RulesConfig { decks: 6, five_card_charlie: true, ..RulesConfig::default() },
+            ui: UiConfig { colorblind_mode: true, ..Default::default() },
+            ..Default::default()
+        };
+        save_settings(path, &settings).unwrap();
+
+        let config = Config::load(path).unwrap();
+        assert_eq!(config.window.width, 800);
+        assert_eq!(config.rules.decks, 6);
+        assert!(config.rules.five_card_charlie);
+        assert!(config.ui.colorblind_mode);
+    }
+}
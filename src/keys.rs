@@ -0,0 +1,48 @@
+use blackjack::config::KeysConfig;
+use sdl2::keyboard::Keycode;
+
+/// The key for each action at the table, resolved from the config once at
+/// startup.
+pub struct KeyBindings {
+    hit: Keycode,
+    stand: Keycode,
+    double: Keycode,
+    split: Keycode,
+    restart: Keycode
+}
+
+impl KeyBindings {
+    pub fn from_config(keys: &KeysConfig) -> Result<KeyBindings, String> {
+        return Ok(KeyBindings {
+            hit: parse_key("hit", &keys.hit)?,
+            stand: parse_key("stand", &keys.stand)?,
+            double: parse_key("double", &keys.double)?,
+            split: parse_key("split", &keys.split)?,
+            restart: parse_key("restart", &keys.restart)?
+        });
+    }
+
+    pub fn hit(&self) -> Keycode {
+        return self.hit;
+    }
+
+    pub fn stand(&self) -> Keycode {
+        return self.stand;
+    }
+
+    pub fn double(&self) -> Keycode {
+        return self.double;
+    }
+
+    pub fn split(&self) -> Keycode {
+        return self.split;
+    }
+
+    pub fn restart(&self) -> Keycode {
+        return self.restart;
+    }
+}
+
+fn parse_key(action: &str, name: &str) -> Result<Keycode, String> {
+    return Keycode::from_name(name).ok_or(format!("keys.{}: unknown key `{}`", action, name));
+}
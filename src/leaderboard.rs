@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const LEADERBOARD_PATH: &str = "leaderboard.toml";
+
+/// How many of the best tournament scores are kept.
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub bankroll: u32
+}
+
+/// Best tournament finishes, highest bankroll first, kept between sessions.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>
+}
+
+impl Leaderboard {
+    /// Reads the leaderboard at `path`; a missing file starts an empty one.
+    pub fn load(path: &str) -> Result<Leaderboard, String> {
+        if !Path::new(path).exists() {
+            return Ok(Leaderboard::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read leaderboard {}: {}", path, err))?;
+
+        return toml::from_str(&content)
+            .map_err(|err: toml::de::Error| format!("invalid leaderboard {}: {}", path, err.message()));
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|err| format!("failed to write leaderboard {}: {}", path, err))?;
+
+        return fs::write(path, content)
+            .map_err(|err| format!("failed to write leaderboard {}: {}", path, err));
+    }
+
+    /// Whether a finish on `bankroll` would make it onto the board.
+    pub fn qualifies(&self, bankroll: u32) -> bool {
+        return self.entries.len() < LEADERBOARD_SIZE || self.entries.iter().any(|entry| bankroll > entry.bankroll);
+    }
+
+    /// Adds a finish in its place, behind any equal score already there,
+    /// dropping whatever falls off the bottom.
+    pub fn insert(&mut self, name: &str, bankroll: u32) {
+        let position = self.entries.iter().position(|entry| bankroll > entry.bankroll).unwrap_or(self.entries.len());
+        self.entries.insert(position, LeaderboardEntry { name: name.to_string(), bankroll: bankroll });
+        self.entries.truncate(LEADERBOARD_SIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaderboard_keeps_the_ten_best_in_order() {
+        let mut leaderboard = Leaderboard::default();
+        for bankroll in 1..=10 {
+            leaderboard.insert("AAA", bankroll * 100);
+        }
+        assert!(!leaderboard.qualifies(100));
+        assert!(leaderboard.qualifies(101));
+
+        leaderboard.insert("BOB", 550);
+        leaderboard.insert("EVE", 550);
+        let names: Vec<&str> = leaderboard.entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names[4..8], ["AAA", "BOB", "EVE", "AAA"]);
+        assert_eq!(leaderboard.entries.len(), 10);
+        assert_eq!(leaderboard.entries[0].bankroll, 1000);
+        assert_eq!(leaderboard.entries[9].bankroll, 300);
+    }
+}
@@ -0,0 +1,2244 @@
+use log::{debug, info};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+pub mod achievements;
+pub mod cache;
+pub mod config;
+pub mod leaderboard;
+pub mod replay;
+pub mod simulation;
+pub mod stats;
+pub mod strategy;
+
+use config::{Dealing, RulesConfig};
+
+pub const TWENTY_ONE: usize = 21;
+pub const SAVE_PATH: &str = "savegame.toml";
+/// The default card set, which every other theme falls back on.
+pub const CARDS_DIR: &str = "assets/cards";
+
+const CHARLIE_CARDS: usize = 5;
+const CUT_CARD_PERCENT: usize = 25;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum CardType {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace
+}
+
+impl CardType {
+    pub fn iterator() -> impl Iterator<Item = CardType> {
+        return [CardType::Two,
+        CardType::Three,
+        CardType::Four,
+        CardType::Five,
+        CardType::Six,
+        CardType::Seven,
+        CardType::Eight,
+        CardType::Nine,
+        CardType::Ten,
+        CardType::Jack,
+        CardType::Queen,
+        CardType::King,
+        CardType::Ace].iter().copied();
+    }
+
+    pub fn get_score(&self) -> usize {
+        return match self {
+            CardType::Two => 2,
+            CardType::Three => 3,
+            CardType::Four => 4,
+            CardType::Five => 5,
+            CardType::Six => 6,
+            CardType::Seven => 7,
+            CardType::Eight => 8,
+            CardType::Nine => 9,
+            CardType::Ten => 10,
+            CardType::Jack | CardType::Queen | CardType::King => 10,
+            CardType::Ace => 11,
+        } 
+    }
+
+    pub fn get_string_name(&self) -> String {
+        return match self {
+            CardType::Two => "2".to_string(),
+            CardType::Three => "3".to_string(),
+            CardType::Four => "4".to_string(),
+            CardType::Five => "5".to_string(),
+            CardType::Six => "6".to_string(),
+            CardType::Seven => "7".to_string(),
+            CardType::Eight => "8".to_string(),
+            CardType::Nine => "9".to_string(),
+            CardType::Ten => "10".to_string(),
+            CardType::Jack => "jack".to_string(),
+            CardType::Queen => "queen".to_string(), 
+            CardType::King => "king".to_string(),
+            CardType::Ace => "ace".to_string(),
+        } 
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum CardSuit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades
+}
+
+impl CardSuit {
+    pub fn iterator() -> impl Iterator<Item = CardSuit> {
+        return [
+            CardSuit::Clubs,
+            CardSuit::Diamonds,
+            CardSuit::Hearts,
+            CardSuit::Spades,
+        ].iter().copied();
+    }
+
+    pub fn get_string_name(&self) -> String {
+        return match self {
+            CardSuit::Clubs => "clubs".to_string(),
+            CardSuit::Diamonds => "diamonds".to_string(),
+            CardSuit::Hearts => "hearts".to_string(),
+            CardSuit::Spades => "spades".to_string(),
+        };
+    }
+
+    /// Single letter naming the suit, for players who can't tell the suits
+    /// apart by colour.
+    pub fn get_letter(&self) -> &'static str {
+        return match self {
+            CardSuit::Clubs => "C",
+            CardSuit::Diamonds => "D",
+            CardSuit::Hearts => "H",
+            CardSuit::Spades => "S",
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChipDenom {
+    Hundred,
+    TwentyFive,
+    Five,
+    One
+}
+
+impl ChipDenom {
+    /// Largest denomination first.
+    pub fn iterator() -> impl Iterator<Item = ChipDenom> {
+        return [
+            ChipDenom::Hundred,
+            ChipDenom::TwentyFive,
+            ChipDenom::Five,
+            ChipDenom::One,
+        ].iter().copied();
+    }
+
+    pub fn value(&self) -> u32 {
+        return match self {
+            ChipDenom::Hundred => 100,
+            ChipDenom::TwentyFive => 25,
+            ChipDenom::Five => 5,
+            ChipDenom::One => 1,
+        };
+    }
+
+    pub fn texture_path(&self) -> String {
+        return format!("assets/chips/chip_{}.png", self.value());
+    }
+}
+
+/// Breaks `amount` into as few chips as possible, largest first.
+pub fn chips_for_amount(amount: u32) -> Vec<ChipDenom> {
+    let mut chips = Vec::<ChipDenom>::new();
+    let mut left = amount;
+    for denom in ChipDenom::iterator() {
+        while left >= denom.value() {
+            chips.push(denom);
+            left -= denom.value();
+        }
+    }
+
+    return chips;
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Card {
+    /// Position in the full deck, telling apart the same card from
+    /// different decks of the shoe.
+    pub id: usize,
+    pub card_type: CardType,
+    pub card_suit: CardSuit,
+    pub path: String
+}
+
+impl Card {
+    /// Such as "queen of hearts", for the logs.
+    pub fn name(&self) -> String {
+        return format!("{} of {}", self.card_type.get_string_name(), self.card_suit.get_string_name());
+    }
+
+    /// Such as "QH", short enough for the hand history. The game font has
+    /// no suit symbols, so the suit is a letter.
+    pub fn short_name(&self) -> String {
+        let rank = match self.card_type {
+            CardType::Jack | CardType::Queen | CardType::King | CardType::Ace => self.card_type.get_string_name()[..1].to_uppercase(),
+            card_type => card_type.get_string_name(),
+        };
+        return format!("{}{}", rank, self.card_suit.get_letter());
+    }
+}
+
+/// The cards the dealer or one of the players holds, in the order they
+/// were dealt, and everything the rules read off them.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Hand {
+    cards: Vec<Card>
+}
+
+impl Hand {
+    pub fn new() -> Hand {
+        return Hand { cards: Vec::<Card>::new() };
+    }
+
+    pub fn push(&mut self, card: Card) {
+        self.cards.push(card);
+    }
+
+    pub fn pop(&mut self) -> Option<Card> {
+        return self.cards.pop();
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        return &self.cards;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.cards.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.cards.is_empty();
+    }
+
+    /// Whether the card with deck position `id` is in the hand.
+    pub fn contains(&self, id: usize) -> bool {
+        return self.cards.iter().any(|card| card.id == id);
+    }
+
+    pub fn card_types(&self) -> Vec<CardType> {
+        return self.cards.iter().map(|card| card.card_type).collect();
+    }
+
+    /// The cards by name followed by the total, for the logs.
+    pub fn describe(&self) -> String {
+        let names: Vec<String> = self.cards.iter().map(|card| card.name()).collect();
+        return format!("{} ({})", names.join(", "), self.score());
+    }
+
+    pub fn value(&self) -> HandValue {
+        return evaluate_hand(&self.card_types());
+    }
+
+    pub fn score(&self) -> usize {
+        return self.value().total;
+    }
+
+    pub fn is_soft(&self) -> bool {
+        return self.value().soft;
+    }
+
+    pub fn is_blackjack(&self) -> bool {
+        return self.value().blackjack;
+    }
+
+    pub fn is_bust(&self) -> bool {
+        return self.value().bust;
+    }
+
+    /// Whether a single extra card could take the hand over 21. Aces can
+    /// always drop to 1, so only the hard total matters and the worst draw
+    /// is a ten.
+    pub fn can_bust(&self) -> bool {
+        let mut hard_total = 0;
+        for card in &self.cards {
+            hard_total += match card.card_type {
+                CardType::Ace => 1,
+                card_type => card_type.get_score(),
+            };
+        }
+
+        return hard_total + CardType::Ten.get_score() > TWENTY_ONE;
+    }
+}
+
+/// One of the players' hands with the stake riding on it. Splitting a pair
+/// turns one hand into two, each with its own bet, for the same seat.
+#[derive(Serialize, Deserialize)]
+pub struct PlayerHand {
+    pub cards: Hand,
+    pub bet: u32,
+    /// Which player the hand belongs to.
+    pub seat: usize,
+    pub surrendered: bool,
+    /// The hand started from a split pair of aces.
+    pub split_aces: bool
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Winner {
+    Player,
+    Casino,
+    Tie
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum RoundReason {
+    PlayerBust,
+    PlayerBlackjack,
+    DealerBlackjack,
+    DealerBust,
+    PlayerHigher,
+    DealerHigher,
+    DealerWinsTie,
+    Push,
+    Surrender,
+    FiveCardCharlie,
+    EvenMoney
+}
+
+/// Outcome of a finished round, kept on the game-over status so rendering
+/// doesn't have to recompute totals.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RoundResult {
+    pub winner: Winner,
+    pub player_total: usize,
+    pub dealer_total: usize,
+    pub reason: RoundReason,
+    pub bet: u32,
+    pub payout: u32
+}
+
+/// A decision the player can make about the active hand.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Action {
+    Hit,
+    Stand,
+    DoubleDown,
+    Split
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HandProgress {
+    Continue,
+    Stand,
+    Bust
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum GameStatus {
+    MainMenu,
+    Settings,
+    Achievements,
+    /// Lifetime totals and what they work out to.
+    Stats,
+    AwaitingBet,
+    /// The cut card came up; the shoe is shuffled before the next deal.
+    Shuffling,
+    Uninitialized,
+    /// Early surrender, offered before the dealer checks for a natural.
+    OfferingEarlySurrender,
+    OfferingInsurance,
+    OfferingEvenMoney,
+    AwaitingPlayerDecision,
+    GameOver(Vec<RoundResult>),
+    PlayerStopedTakingCards,
+    /// A tournament's hands are all played; its final bankroll is up for
+    /// the leaderboard.
+    TournamentOver,
+    /// No player can cover the minimum bet any more; only a restart or
+    /// quitting leads out of here.
+    Bankrupt,
+    /// The pause menu, over the status play resumes in.
+    Paused(Box<GameStatus>)
+}
+
+impl GameStatus {
+    /// The dealer's second card stays face down until the player is done.
+    pub fn hole_card_hidden(&self) -> bool {
+        return match self {
+            GameStatus::OfferingEarlySurrender | GameStatus::OfferingInsurance | GameStatus::OfferingEvenMoney
+                | GameStatus::AwaitingPlayerDecision => true,
+            GameStatus::Paused(previous) => previous.hole_card_hidden(),
+            _ => false,
+        };
+    }
+}
+
+/// The cards and money on the table, without anything to do with drawing
+/// them. The front end moves it through a round by calling the player
+/// actions below and reading `status`.
+#[derive(Serialize, Deserialize)]
+pub struct Table {
+    pub status: GameStatus,
+    pub deck: Vec<Card>,
+    shoe: Vec<usize>,
+    /// Saved mid-stream, so a loaded table deals the same cards as this one.
+    #[serde(serialize_with = "save_rng", deserialize_with = "load_rng")]
+    rng: ChaCha12Rng,
+    pub rules: RulesConfig,
+    pub player_hands: Vec<PlayerHand>,
+    pub active_hand: usize,
+    pub casino_hand: Hand,
+    /// One bankroll per seated player.
+    pub bankrolls: Vec<u32>,
+    pub current_bet: u32,
+    pub insurance_bet: u32,
+    /// Hi-Lo count of every card seen since the last shuffle.
+    pub running_count: i32,
+    /// Rounds settled since the last restart.
+    pub hands_played: u32,
+    /// Largest bankroll seen since the last restart.
+    pub peak_bankroll: u32,
+    /// What has happened this round, a line per card or decision.
+    pub history: Vec<String>
+}
+
+impl Table {
+    /// Seats `rules.players` players with `bankroll` each. The same `seed`
+    /// deals the same sequence of cards.
+    pub fn new(rules: RulesConfig, bankroll: u32, bet: u32, seed: u64) -> Table {
+        let deck = get_deck(rules.decks);
+        let mut table = Table {
+            status: GameStatus::MainMenu,
+            deck: deck,
+            shoe: Vec::<usize>::new(),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            player_hands: Vec::<PlayerHand>::new(),
+            active_hand: 0,
+            casino_hand: Hand::new(),
+            bankrolls: vec![bankroll; rules.players],
+            current_bet: bet,
+            insurance_bet: 0,
+            running_count: 0,
+            hands_played: 0,
+            peak_bankroll: bankroll,
+            history: Vec::<String>::new(),
+            rules: rules
+        };
+        table.shoe = table.shuffle_deck();
+
+        return table;
+    }
+
+    /// Writes the whole table to `path`, leaving the game in progress as it
+    /// is: the saved copy deals the same cards from here on.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|err| format!("failed to write saved game {}: {}", path, err))?;
+
+        return fs::write(path, content)
+            .map_err(|err| format!("failed to write saved game {}: {}", path, err));
+    }
+
+    pub fn load(path: &str) -> Result<Table, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read saved game {}: {}", path, err))?;
+
+        let table: Table = toml::from_str(&content)
+            .map_err(|err: toml::de::Error| format!("invalid saved game {}: {}", path, err.message()))?;
+
+        return Ok(table);
+    }
+
+    /// Takes the current bet from every player who can cover it; anyone
+    /// who can't sits the round out. The cards are dealt on the next `deal`,
+    /// after a shuffle if the cut card has come up.
+    pub fn place_bet(&mut self) {
+        let bet = self.current_bet;
+        self.player_hands = Vec::<PlayerHand>::new();
+        for seat in 0..self.bankrolls.len() {
+            if self.bankrolls[seat] >= bet {
+                self.bankrolls[seat] -= bet;
+                self.player_hands.push(PlayerHand { cards: Hand::new(), bet: bet, seat: seat, surrendered: false, split_aces: false });
+            }
+        }
+
+        debug!("bet of {} placed for {} hand(s)", bet, self.player_hands.len());
+
+        self.status = if needs_reshuffle(self.shoe.len(), self.deck.len()) {
+            GameStatus::Shuffling
+        } else {
+            GameStatus::Uninitialized
+        };
+    }
+
+    /// Puts every card off the table back in the shoe and starts the count over.
+    pub fn reshuffle(&mut self) {
+        self.shoe = self.shuffle_deck();
+        self.running_count = 0;
+        info!("shoe reshuffled, {} cards", self.shoe.len());
+    }
+
+    /// Whether the current bet is within the table limits and at least one
+    /// player can cover it.
+    pub fn can_place_bet(&self, min_bet: u32, max_bet: u32) -> bool {
+        return self.current_bet >= min_bet.max(1) && self.current_bet <= max_bet
+            && self.current_bet <= self.largest_bankroll();
+    }
+
+    pub fn largest_bankroll(&self) -> u32 {
+        return self.bankrolls.iter().copied().max().unwrap_or(0);
+    }
+
+    /// Whether nobody can cover even `min_bet` any more.
+    pub fn is_bankrupt(&self, min_bet: u32) -> bool {
+        return self.largest_bankroll() < min_bet.max(1);
+    }
+
+    /// The player whose hand is being played.
+    pub fn active_seat(&self) -> usize {
+        return self.player_hands[self.active_hand].seat;
+    }
+
+    /// Deals the opening two cards to every hand and the dealer. The shoe is
+    /// reshuffled first at the cut card, or whenever it is too short for the
+    /// opening deal, so none of these draws can come up empty.
+    pub fn deal(&mut self) {
+        let opening_cards = 2 * (self.player_hands.len() + 1);
+        if needs_reshuffle(self.shoe.len(), self.deck.len()) || self.shoe.len() < opening_cards {
+            self.reshuffle();
+        }
+
+        self.active_hand = 0;
+
+        let mut random_card = self.get_random_card().unwrap();
+        self.casino_hand.push(random_card);
+
+        for _ in 0..2 {
+            for hand in 0..self.player_hands.len() {
+                random_card = self.get_random_card().unwrap();
+                self.player_hands[hand].cards.push(random_card);
+            }
+        }
+
+        if self.rules.dealing == Dealing::American {
+            random_card = self.get_random_card().unwrap();
+            // The hole card is face down, so it only counts once it is turned over.
+            self.running_count -= hi_lo_value(random_card.card_type);
+            self.casino_hand.push(random_card);
+        }
+
+        debug!("dealer shows {}", self.casino_hand.cards()[0].name());
+        for (idx, hand) in self.player_hands.iter().enumerate() {
+            debug!("hand {} (seat {}) dealt {}", idx, hand.seat, hand.cards.describe());
+        }
+        for idx in 0..self.player_hands.len() {
+            let cards = short_names(self.player_hands[idx].cards.cards());
+            self.history.push(format!("{} dealt {}", self.hand_label(idx), cards));
+        }
+        self.history.push(format!("Dealer shows {}", self.casino_hand.cards()[0].short_name()));
+
+        if self.can_surrender_early() {
+            self.status = GameStatus::OfferingEarlySurrender;
+            return;
+        }
+
+        self.offer_insurance_or_start();
+    }
+
+    /// Early surrender is only worth offering against an ace or a ten, and
+    /// like insurance only to a player alone at the table. A natural has
+    /// nothing to give up.
+    fn can_surrender_early(&self) -> bool {
+        let upcard = self.casino_hand.cards()[0].card_type;
+        return self.rules.early_surrender && self.player_hands.len() == 1 && upcard.get_score() >= 10
+            && !self.player_hands[0].cards.is_blackjack();
+    }
+
+    /// Settles the early surrender offer. Giving up ends the round for half
+    /// the bet back, even against a dealer natural; otherwise play goes on
+    /// to insurance and the dealer's peek.
+    pub fn resolve_early_surrender(&mut self, accepted: bool) {
+        if accepted {
+            self.player_hands[0].surrendered = true;
+            self.history.push(format!("{} surrenders early", self.hand_label(0)));
+            self.finish_round();
+            return;
+        }
+
+        self.history.push("Early surrender declined".to_string());
+        self.offer_insurance_or_start();
+    }
+
+    fn offer_insurance_or_start(&mut self) {
+        // Without a hole card there's nothing to insure against yet.
+        if self.rules.dealing == Dealing::European {
+            self.start_player_turn();
+            return;
+        }
+
+        // Insurance and even money are only offered to a player alone at the table.
+        let insurance_bet = insurance_stake(self.current_bet);
+        let single_player = self.player_hands.len() == 1;
+        if let CardType::Ace = self.casino_hand.cards()[0].card_type {
+            if single_player && self.player_hands[0].cards.score() == TWENTY_ONE {
+                self.status = GameStatus::OfferingEvenMoney;
+                return;
+            }
+            if single_player && insurance_bet > 0 && self.bankrolls[self.player_hands[0].seat] >= insurance_bet {
+                self.status = GameStatus::OfferingInsurance;
+                return;
+            }
+        }
+
+        self.start_player_turn();
+    }
+
+    /// The dealer peeks first: a dealer natural ends the round before anyone
+    /// acts, beating every hand but another natural. Dealt European style
+    /// there's no hole card to peek at, and without the peek a natural only
+    /// shows once the players are done.
+    fn start_player_turn(&mut self) {
+        self.active_hand = 0;
+        if self.rules.dealer_peeks && self.casino_hand.is_blackjack() {
+            debug!("dealer peeks a natural");
+            self.finish_round();
+            return;
+        }
+
+        self.status = GameStatus::AwaitingPlayerDecision;
+
+        // A natural has nothing to decide.
+        if self.player_hands[0].cards.score() == TWENTY_ONE {
+            self.advance_hand();
+        }
+    }
+
+    /// Settles the insurance offer. A dealer natural ends the round right
+    /// away, whether or not insurance was taken. Without the peek the stake
+    /// waits for the hole card at the end of the round.
+    pub fn resolve_insurance(&mut self, accepted: bool) {
+        let insurance_bet = insurance_stake(self.current_bet);
+        let dealer_natural = self.rules.dealer_peeks && self.casino_hand.score() == TWENTY_ONE;
+        let seat = self.player_hands[0].seat;
+        self.history.push(if accepted { "Insurance taken" } else { "Insurance declined" }.to_string());
+        if accepted {
+            self.bankrolls[seat] -= insurance_bet;
+            self.insurance_bet = insurance_bet;
+            if dealer_natural {
+                self.bankrolls[seat] += settle_insurance(insurance_bet);
+            }
+        }
+
+        if dealer_natural {
+            self.finish_round();
+        } else {
+            self.start_player_turn();
+        }
+    }
+
+    /// Even money settles a natural against the dealer's ace at 1:1 before
+    /// the hole card is checked; declining plays the natural out as usual.
+    pub fn resolve_even_money(&mut self, accepted: bool) {
+        self.history.push(if accepted { "Even money taken" } else { "Even money declined" }.to_string());
+        if !accepted {
+            self.finish_round();
+            return;
+        }
+
+        let hand = &self.player_hands[0];
+        let result = RoundResult {
+            winner: Winner::Player,
+            player_total: TWENTY_ONE,
+            dealer_total: self.casino_hand.score(),
+            reason: RoundReason::EvenMoney,
+            bet: hand.bet,
+            payout: settle_bet(Winner::Player, hand.bet)
+        };
+
+        self.bankrolls[hand.seat] += result.payout;
+        self.reveal_hole_card();
+        self.record_settled_round();
+        self.status = GameStatus::GameOver(vec![result]);
+    }
+
+    pub fn can_double(&self) -> bool {
+        let hand = &self.player_hands[self.active_hand];
+        return self.can_hit() && can_double_down(hand.cards.len(), self.bankrolls[hand.seat], hand.bet);
+    }
+
+    /// Split aces that only get one card can't be hit; they are only ever
+    /// up for a decision when they can be split again.
+    pub fn can_hit(&self) -> bool {
+        return !(self.player_hands[self.active_hand].split_aces && self.rules.split_aces_one_card);
+    }
+
+    pub fn can_split(&self) -> bool {
+        return self.can_split_hand(self.active_hand);
+    }
+
+    /// A pair can be split while the player has fewer than
+    /// `rules.max_split_hands` hands, aces only once unless resplitting
+    /// them is allowed.
+    fn can_split_hand(&self, idx: usize) -> bool {
+        let hand = &self.player_hands[idx];
+        return self.seat_hand_count(hand.seat) < self.rules.max_split_hands && self.bankrolls[hand.seat] >= hand.bet
+            && is_pair(&hand.cards.card_types()) && (!hand.split_aces || self.rules.resplit_aces);
+    }
+
+    /// Whether the hand at `idx` still has anything for the player to decide.
+    fn needs_decision(&self, idx: usize) -> bool {
+        let hand = &self.player_hands[idx];
+        if hand.cards.score() >= TWENTY_ONE {
+            return false;
+        }
+        if hand.split_aces && self.rules.split_aces_one_card {
+            return self.can_split_hand(idx);
+        }
+
+        return true;
+    }
+
+    pub fn can_surrender(&self) -> bool {
+        let hand = &self.player_hands[self.active_hand];
+        return self.rules.surrender_allowed && self.seat_hand_count(hand.seat) == 1 && hand.cards.len() == 2;
+    }
+
+    fn seat_hand_count(&self, seat: usize) -> usize {
+        return self.player_hands.iter().filter(|hand| hand.seat == seat).count();
+    }
+
+    /// Two cards to 21 after a split is not a blackjack.
+    fn is_natural(&self, hand: &PlayerHand) -> bool {
+        return hand.cards.is_blackjack() && self.seat_hand_count(hand.seat) == 1;
+    }
+
+    pub fn hit(&mut self) {
+        self.player_draw(false);
+    }
+
+    pub fn double_down(&mut self) {
+        let hand = &mut self.player_hands[self.active_hand];
+        self.bankrolls[hand.seat] -= hand.bet;
+        hand.bet *= 2;
+        self.player_draw(true);
+    }
+
+    fn player_draw(&mut self, doubled: bool) {
+        let random_card = self.get_random_card().unwrap();
+        self.player_hands[self.active_hand].cards.push(random_card);
+
+        let hand = &self.player_hands[self.active_hand];
+        let player_score = hand.cards.score();
+        let verb = if doubled { "doubles" } else { "hits" };
+        debug!("hand {} {} to {}", self.active_hand, verb, hand.cards.describe());
+        let line = format!("{} {}: {} ({})", self.hand_label(self.active_hand), verb, hand.cards.cards().last().unwrap().short_name(), player_score);
+        self.history.push(line);
+        if is_five_card_charlie(hand.cards.len(), player_score, &self.rules) {
+            self.advance_hand();
+            return;
+        }
+
+        match hand_progress(player_score, doubled) {
+            HandProgress::Bust | HandProgress::Stand => self.advance_hand(),
+            HandProgress::Continue => {}
+        }
+    }
+
+    /// Gives up the active hand for half its bet back, paid out with the
+    /// rest of the round.
+    pub fn surrender(&mut self) {
+        self.player_hands[self.active_hand].surrendered = true;
+        debug!("hand {} surrendered", self.active_hand);
+        self.history.push(format!("{} surrenders", self.hand_label(self.active_hand)));
+        self.advance_hand();
+    }
+
+    pub fn split(&mut self) {
+        let bet = self.player_hands[self.active_hand].bet;
+        let seat = self.player_hands[self.active_hand].seat;
+        self.history.push(format!("{} splits", self.hand_label(self.active_hand)));
+        let second_card = self.player_hands[self.active_hand].cards.pop().unwrap();
+        self.bankrolls[seat] -= bet;
+        let split_aces = matches!(second_card.card_type, CardType::Ace);
+        let mut split_hand = Hand::new();
+        split_hand.push(second_card);
+        self.player_hands[self.active_hand].split_aces = split_aces;
+        self.player_hands.insert(self.active_hand + 1, PlayerHand {
+            cards: split_hand, bet: bet, seat: seat, surrendered: false, split_aces: split_aces
+        });
+
+        for hand in self.active_hand..=self.active_hand + 1 {
+            let random_card = self.get_random_card().unwrap();
+            self.player_hands[hand].cards.push(random_card);
+            debug!("split hand {} dealt {}", hand, self.player_hands[hand].cards.describe());
+            let line = format!("{} dealt {}", self.hand_label(hand), short_names(self.player_hands[hand].cards.cards()));
+            self.history.push(line);
+        }
+
+        if !self.needs_decision(self.active_hand) {
+            self.advance_hand();
+        }
+    }
+
+    pub fn stand(&mut self) {
+        self.history.push(format!("{} stands", self.hand_label(self.active_hand)));
+        self.advance_hand();
+    }
+
+    /// Moves on from the active hand: to the next hand, split or another
+    /// player's, that still has a decision to make, or to the dealer once
+    /// every hand is done.
+    fn advance_hand(&mut self) {
+        while self.active_hand + 1 < self.player_hands.len() {
+            self.active_hand += 1;
+            if self.needs_decision(self.active_hand) {
+                return;
+            }
+        }
+
+        // The dealer only draws while some hand still depends on their total.
+        let all_settled = self.player_hands.iter().all(|hand| {
+            let score = hand.cards.score();
+            return hand.cards.is_bust() || hand.surrendered || self.is_natural(hand)
+                || is_five_card_charlie(hand.cards.len(), score, &self.rules);
+        });
+        if all_settled {
+            // A natural still has to see whether the dealer's second card
+            // makes one too.
+            let natural = self.player_hands.iter().any(|hand| self.is_natural(hand));
+            if natural && self.casino_hand.len() < 2 {
+                let random_card = self.get_random_card().unwrap();
+                self.casino_hand.push(random_card);
+            }
+            self.finish_round();
+        } else {
+            self.status = GameStatus::PlayerStopedTakingCards;
+        }
+    }
+
+    /// Plays the dealer's hand out in one go.
+    pub fn play_dealer(&mut self) {
+        while let GameStatus::PlayerStopedTakingCards = self.status {
+            self.dealer_step();
+        }
+    }
+
+    /// Plays a single step of the dealer's hand: one draw while they still
+    /// have to hit, otherwise standing and settling the round.
+    pub fn dealer_step(&mut self) {
+        if dealer_should_hit(&self.casino_hand.card_types(), &self.rules) {
+            let random_card = self.get_random_card().unwrap();
+            self.casino_hand.push(random_card);
+            return;
+        }
+        debug!("dealer stands on {}", self.casino_hand.describe());
+
+        self.finish_round();
+    }
+
+    fn finish_round(&mut self) {
+        let casino_score = self.casino_hand.score();
+        let dealer_natural = self.casino_hand.is_blackjack();
+
+        let mut results = Vec::<RoundResult>::new();
+        for hand in &self.player_hands {
+            let player_score = hand.cards.score();
+            let player_natural = self.is_natural(hand);
+            if hand.surrendered {
+                results.push(RoundResult {
+                    winner: Winner::Casino,
+                    player_total: player_score,
+                    dealer_total: casino_score,
+                    reason: RoundReason::Surrender,
+                    bet: hand.bet,
+                    payout: surrender_refund(hand.bet)
+                });
+            } else if is_five_card_charlie(hand.cards.len(), player_score, &self.rules) {
+                results.push(RoundResult {
+                    winner: Winner::Player,
+                    player_total: player_score,
+                    dealer_total: casino_score,
+                    reason: RoundReason::FiveCardCharlie,
+                    bet: hand.bet,
+                    payout: settle_bet(Winner::Player, hand.bet)
+                });
+            } else {
+                results.push(resolve_round(player_score, casino_score, player_natural, dealer_natural, hand.bet, &self.rules));
+            }
+        }
+
+        // Insurance taken against a dealer who didn't peek is settled here.
+        if !self.rules.dealer_peeks && dealer_natural && self.insurance_bet > 0 {
+            let seat = self.player_hands[0].seat;
+            self.bankrolls[seat] += settle_insurance(self.insurance_bet);
+        }
+        for (hand, result) in self.player_hands.iter().zip(&results) {
+            self.bankrolls[hand.seat] += result.payout;
+            info!("seat {}: {} against dealer {}: {:?} ({:?}), bet {}, paid {}", hand.seat, hand.cards.describe(),
+                self.casino_hand.describe(), result.winner, result.reason, result.bet, result.payout);
+        }
+        self.reveal_hole_card();
+        for (idx, result) in results.iter().enumerate() {
+            let outcome = match result.winner {
+                Winner::Player => format!("wins {}", result.payout.saturating_sub(result.bet)),
+                Winner::Casino => format!("loses {}", result.bet.saturating_sub(result.payout)),
+                Winner::Tie => "pushes".to_string(),
+            };
+            self.history.push(format!("{} {}", self.hand_label(idx), outcome));
+        }
+        self.record_settled_round();
+        self.status = GameStatus::GameOver(results);
+    }
+
+    fn record_settled_round(&mut self) {
+        self.hands_played += 1;
+        self.peak_bankroll = self.peak_bankroll.max(self.largest_bankroll());
+    }
+
+    /// Counts the hole card now that it's face up. The history gets it
+    /// here, followed by any cards the dealer drew after it; without a hole
+    /// card every card after the first was a draw.
+    fn reveal_hole_card(&mut self) {
+        let cards = self.casino_hand.cards();
+        let types = self.casino_hand.card_types();
+        let first_drawn = match self.rules.dealing {
+            Dealing::American => {
+                self.running_count += hi_lo_value(cards[1].card_type);
+                self.history.push(format!("Dealer reveals {} ({})", cards[1].short_name(), score_cards(&types[..2])));
+                2
+            },
+            Dealing::European => 1,
+        };
+        for drawn in first_drawn..cards.len() {
+            self.history.push(format!("Dealer hits: {} ({})", cards[drawn].short_name(), score_cards(&types[..=drawn])));
+        }
+    }
+
+    /// "Player" with a single hand on the table, otherwise the hand's number.
+    fn hand_label(&self, idx: usize) -> String {
+        if self.player_hands.len() == 1 {
+            return "Player".to_string();
+        }
+
+        return format!("Hand {}", idx + 1);
+    }
+
+    /// Takes new rules for the coming rounds; a different number of decks
+    /// brings a fresh shoe.
+    pub fn set_rules(&mut self, rules: RulesConfig) {
+        let decks_changed = rules.decks != self.rules.decks;
+        self.rules = rules;
+        if decks_changed {
+            self.deck = get_deck(self.rules.decks);
+            self.shoe = self.shuffle_deck();
+            self.running_count = 0;
+        }
+    }
+
+    /// Calls off the round in play as if every hand pushed, handing back all
+    /// its bets, and bets the same again for a fresh deal, after a shuffle
+    /// if asked for. Only for testing; nothing about the round is recorded.
+    pub fn redeal(&mut self, reshuffle: bool) {
+        for hand in &self.player_hands {
+            self.bankrolls[hand.seat] += hand.bet;
+        }
+        if self.insurance_bet > 0 {
+            self.bankrolls[self.player_hands[0].seat] += self.insurance_bet;
+        }
+        // The hole card was dealt, so the count still has to see it.
+        if self.status.hole_card_hidden() && self.casino_hand.len() > 1 {
+            self.running_count += hi_lo_value(self.casino_hand.cards()[1].card_type);
+        }
+        info!("round called off, bets returned");
+
+        self.clear_round();
+        if reshuffle {
+            self.reshuffle();
+        }
+        self.place_bet();
+    }
+
+    /// Starts over with every player back at `bankroll`, keeping the shoe.
+    pub fn restart(&mut self, bankroll: u32) {
+        self.clear_round();
+        self.bankrolls = vec![bankroll; self.bankrolls.len()];
+        self.hands_played = 0;
+        self.peak_bankroll = bankroll;
+    }
+
+    /// Cards left in the shoe before the next shuffle.
+    pub fn cards_remaining(&self) -> usize {
+        return self.shoe.len();
+    }
+
+    /// Chance, from 0 to 1, that the next card busts the active hand. Every
+    /// card the player hasn't seen could come next, the hole card included.
+    pub fn bust_probability(&self) -> f32 {
+        let mut unseen: Vec<usize> = self.shoe.clone();
+        if self.status.hole_card_hidden() && self.casino_hand.len() > 1 {
+            unseen.push(self.casino_hand.cards()[1].id);
+        }
+        if unseen.is_empty() {
+            return 0.0;
+        }
+
+        let hand = &self.player_hands[self.active_hand].cards;
+        let busting = unseen.iter()
+            .filter(|card| {
+                let mut next = hand.clone();
+                next.push(self.deck[**card].clone());
+                return next.is_bust();
+            })
+            .count();
+
+        return busting as f32 / unseen.len() as f32;
+    }
+
+    /// Cards played in earlier rounds and not yet shuffled back in.
+    pub fn cards_discarded(&self) -> usize {
+        return self.deck.len() - self.shoe.len() - self.cards_on_table().len();
+    }
+
+    /// Running count per deck still in the shoe.
+    pub fn true_count(&self) -> f32 {
+        let decks_remaining = (self.shoe.len() as f32 / 52.0).max(0.5);
+        return self.running_count as f32 / decks_remaining;
+    }
+
+    /// Clears the table for the next round's bet.
+    pub fn clear_round(&mut self) {
+        self.status = GameStatus::AwaitingBet;
+        self.current_bet = self.current_bet.min(self.largest_bankroll());
+        self.player_hands = Vec::<PlayerHand>::new();
+        self.active_hand = 0;
+        self.insurance_bet = 0;
+        self.casino_hand = Hand::new();
+        self.history = Vec::<String>::new();
+    }
+
+    /// Every card of the deck that isn't on the table, by index, in a fresh
+    /// random order.
+    fn shuffle_deck(&mut self) -> Vec<usize> {
+        let on_table = self.cards_on_table();
+        let mut shoe: Vec<usize> = (0..self.deck.len()).filter(|card| !on_table.contains(card)).collect();
+        shoe.shuffle(&mut self.rng);
+
+        return shoe;
+    }
+
+    /// Deck indexes of every card in a hand, the dealer's included.
+    fn cards_on_table(&self) -> Vec<usize> {
+        return self.player_hands.iter()
+            .flat_map(|hand| hand.cards.cards().iter())
+            .chain(self.casino_hand.cards().iter())
+            .map(|card| card.id)
+            .collect();
+    }
+
+    fn get_random_card(&mut self) -> Option<Card> {
+        // The cut card normally comes up between rounds; a long round can
+        // still empty the shoe, in which case the discards are reshuffled.
+        if self.shoe.is_empty() {
+            self.shoe = self.shuffle_deck();
+            self.running_count = 0;
+        }
+
+        let card = self.deck[self.shoe.pop()?].clone();
+        self.running_count += hi_lo_value(card.card_type);
+
+        return Some(card);
+    }
+
+    /// The dealer's total as the player sees it: only the up card counts
+    /// while the hole card is face down.
+    pub fn dealer_visible_score(&self) -> usize {
+        if self.status.hole_card_hidden() {
+            return score_cards(&self.casino_hand.card_types()[..1]);
+        }
+
+        return self.casino_hand.score();
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct HandValue {
+    pub total: usize,
+    /// An Ace is still being counted as 11.
+    pub soft: bool,
+    pub bust: bool,
+    /// 21 on exactly two cards. Whether that pays as a natural also depends
+    /// on the hand not coming from a split, which the cards alone can't tell.
+    pub blackjack: bool
+}
+
+impl HandValue {
+    /// The total as shown at the table, e.g. "Soft 17" while an Ace still
+    /// counts as 11.
+    pub fn label(&self) -> String {
+        if self.soft {
+            return format!("Soft {}", self.total);
+        }
+
+        return self.total.to_string();
+    }
+}
+
+fn short_names(cards: &[Card]) -> String {
+    let names: Vec<String> = cards.iter().map(|card| card.short_name()).collect();
+    return names.join(", ");
+}
+
+/// Everything the rules need to know about a hand's cards. Every Ace starts
+/// at 11 and is demoted to 1, one at a time, while the total is over 21.
+pub fn evaluate_hand(cards: &[CardType]) -> HandValue {
+    let mut total = 0;
+    let mut aces = 0;
+    for card_type in cards {
+        if let CardType::Ace = card_type {
+            aces += 1;
+        }
+
+        total += card_type.get_score();
+    }
+
+    while total > TWENTY_ONE && aces > 0 {
+        total -= 10;
+        aces -= 1;
+    }
+
+    return HandValue {
+        total: total,
+        soft: aces > 0,
+        bust: total > TWENTY_ONE,
+        blackjack: cards.len() == 2 && total == TWENTY_ONE
+    };
+}
+
+/// Best total for a hand, see `evaluate_hand`.
+pub fn score_cards(card_types: &[CardType]) -> usize {
+    return evaluate_hand(card_types).total;
+}
+
+
+/// The dealer plays a fixed strategy: draw until reaching the stop score,
+/// whatever the player is holding. With `dealer_hits_soft_17` a soft total
+/// equal to the stop score is drawn on as well.
+pub fn dealer_should_hit(dealer_cards: &[CardType], rules: &RulesConfig) -> bool {
+    let hand = evaluate_hand(dealer_cards);
+    return hand.total < rules.dealer_stop_score
+        || (rules.dealer_hits_soft_17 && hand.soft && hand.total == rules.dealer_stop_score);
+}
+
+/// A double is only offered on the opening two cards and must be covered
+/// by the bankroll.
+pub fn can_double_down(hand_len: usize, bankroll: u32, bet: u32) -> bool {
+    return hand_len == 2 && bankroll >= bet;
+}
+
+/// What happens to the player's hand after it takes a card. A doubled hand
+/// gets exactly one card and then stands.
+pub fn hand_progress(player_total: usize, doubled: bool) -> HandProgress {
+    if player_total > TWENTY_ONE {
+        return HandProgress::Bust;
+    } else if player_total == TWENTY_ONE || doubled {
+        return HandProgress::Stand;
+    }
+
+    return HandProgress::Continue;
+}
+
+/// Under the five-card Charlie rule a hand of five cards that hasn't busted
+/// wins whatever the dealer holds.
+pub fn is_five_card_charlie(hand_len: usize, player_total: usize, rules: &RulesConfig) -> bool {
+    return rules.five_card_charlie && hand_len == CHARLIE_CARDS && player_total <= TWENTY_ONE;
+}
+
+/// Winnings on a natural at `ratio` to one, rounded down to a whole chip.
+pub fn blackjack_winnings(bet: u32, ratio: (u32, u32)) -> u32 {
+    return bet * ratio.0 / ratio.1;
+}
+
+/// `bet` brought within the table limits and what `bankroll` can cover.
+/// A bankroll below the minimum leaves a bet too small to place.
+pub fn clamp_bet(bet: u32, min_bet: u32, max_bet: u32, bankroll: u32) -> u32 {
+    return bet.max(min_bet).min(max_bet).min(bankroll);
+}
+
+/// Amount returned to the bankroll for a round staked with `bet`, which was
+/// taken from the bankroll when the cards were dealt.
+pub fn settle_bet(winner: Winner, bet: u32) -> u32 {
+    return match winner {
+        Winner::Player => bet * 2,
+        Winner::Tie => bet,
+        Winner::Casino => 0,
+    };
+}
+
+/// Half the bet comes back on a surrender; odd bets round down.
+pub fn surrender_refund(bet: u32) -> u32 {
+    return bet / 2;
+}
+
+/// Whether the cut card has come up: fewer than `CUT_CARD_PERCENT` of the
+/// shoe is left to deal.
+pub fn needs_reshuffle(remaining: usize, shoe_size: usize) -> bool {
+    return remaining * 100 < shoe_size * CUT_CARD_PERCENT;
+}
+
+/// Hi-Lo card counting: low cards leaving the shoe favour the player, tens
+/// and aces leaving it favour the house.
+pub fn hi_lo_value(card_type: CardType) -> i32 {
+    return match card_type {
+        CardType::Two | CardType::Three | CardType::Four | CardType::Five | CardType::Six => 1,
+        CardType::Seven | CardType::Eight | CardType::Nine => 0,
+        _ => -1,
+    };
+}
+
+/// Insurance costs half the main bet, rounded down.
+pub fn insurance_stake(bet: u32) -> u32 {
+    return bet / 2;
+}
+
+/// What a winning insurance bet returns: the stake plus 2:1.
+pub fn settle_insurance(insurance_bet: u32) -> u32 {
+    return insurance_bet * 3;
+}
+
+/// Whether the two-card hand can be split into two hands.
+pub fn is_pair(card_types: &[CardType]) -> bool {
+    return card_types.len() == 2 && card_types[0].get_string_name() == card_types[1].get_string_name();
+}
+
+/// Sums up a round played over several hands: the player wins if the hands
+/// paid back more than was staked on them.
+pub fn overall_winner(results: &[RoundResult]) -> Winner {
+    let staked: u32 = results.iter().map(|result| result.bet).sum();
+    let paid: u32 = results.iter().map(|result| result.payout).sum();
+    if paid > staked {
+        return Winner::Player;
+    } else if paid < staked {
+        return Winner::Casino;
+    }
+
+    return Winner::Tie;
+}
+
+/// Decides the round from the final totals. A natural (21 on the first two
+/// cards) beats any other 21, and two naturals push. With `dealer_wins_ties`
+/// every other push goes to the casino.
+pub fn resolve_round(player_total: usize, dealer_total: usize, player_natural: bool, dealer_natural: bool, bet: u32, rules: &RulesConfig) -> RoundResult {
+    let (winner, reason) = if player_total > TWENTY_ONE {
+        (Winner::Casino, RoundReason::PlayerBust)
+    } else if player_natural && dealer_natural {
+        (Winner::Tie, RoundReason::Push)
+    } else if player_natural {
+        (Winner::Player, RoundReason::PlayerBlackjack)
+    } else if dealer_natural {
+        (Winner::Casino, RoundReason::DealerBlackjack)
+    } else if dealer_total > TWENTY_ONE {
+        (Winner::Player, RoundReason::DealerBust)
+    } else if dealer_total > player_total {
+        (Winner::Casino, RoundReason::DealerHigher)
+    } else if dealer_total < player_total {
+        (Winner::Player, RoundReason::PlayerHigher)
+    } else if rules.dealer_wins_ties {
+        (Winner::Casino, RoundReason::DealerWinsTie)
+    } else {
+        (Winner::Tie, RoundReason::Push)
+    };
+
+    let payout = match reason {
+        RoundReason::PlayerBlackjack => bet + blackjack_winnings(bet, rules.blackjack_payout),
+        _ => settle_bet(winner, bet),
+    };
+
+    return RoundResult {
+        winner: winner,
+        player_total: player_total,
+        dealer_total: dealer_total,
+        reason: reason,
+        bet: bet,
+        payout: payout
+    };
+}
+
+/// Where a `ChaCha12Rng` is up to, in a form TOML can hold.
+#[derive(Serialize, Deserialize)]
+struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u64
+}
+
+fn save_rng<S: serde::Serializer>(rng: &ChaCha12Rng, serializer: S) -> Result<S::Ok, S::Error> {
+    // TOML integers are signed 64-bit; a game never gets near that many words.
+    let state = RngState {
+        seed: rng.get_seed(),
+        stream: rng.get_stream(),
+        word_pos: rng.get_word_pos() as u64
+    };
+
+    return state.serialize(serializer);
+}
+
+fn load_rng<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<ChaCha12Rng, D::Error> {
+    let state = RngState::deserialize(deserializer)?;
+    let mut rng = ChaCha12Rng::from_seed(state.seed);
+    rng.set_stream(state.stream);
+    rng.set_word_pos(state.word_pos as u128);
+
+    return Ok(rng);
+}
+
+/// Builds a shoe of `num_decks` full 52-card decks.
+pub fn get_deck(num_decks: usize) -> Vec::<Card> {
+    return build_deck(&[], num_decks);
+}
+
+/// Builds a shoe of `num_decks` decks with every card of the `excluded`
+/// ranks taken out, e.g. the tens for Spanish 21.
+pub fn build_deck(excluded: &[CardType], num_decks: usize) -> Vec::<Card> {
+    let mut vec = Vec::<Card>::new();
+    for _ in 0..num_decks {
+        for tp in CardType::iterator().filter(|tp| !excluded.contains(tp)) {
+            for suit in CardSuit::iterator() {
+                let texture_path = tp.get_string_name() + "_of_" + suit.get_string_name().as_str() + ".png";
+                vec.push(Card { id: vec.len(), card_type: tp, card_suit: suit, path: format!("{}/{}", CARDS_DIR, texture_path) })
+            }
+        }
+    }
+
+    return vec
+}
+
+/// Image paths of `deck` with no file behind them, each listed once.
+pub fn missing_card_assets(deck: &[Card]) -> Vec<String> {
+    let mut missing = Vec::<String>::new();
+    for card in deck {
+        if !std::path::Path::new(&card.path).exists() && !missing.contains(&card.path) {
+            missing.push(card.path.clone());
+        }
+    }
+
+    return missing;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievements::{Achievement, AchievementProgress};
+    use crate::replay::{Replay, ReplayEvent};
+    use crate::simulation::simulate;
+    use crate::strategy::{basic_strategy, basic_strategy_allowing, suggest};
+
+    fn dealer_wins_ties() -> RulesConfig {
+        return RulesConfig { dealer_wins_ties: true, ..RulesConfig::default() };
+    }
+
+    #[test]
+    fn two_aces_count_as_twelve() {
+        assert_eq!(score_cards(&[CardType::Ace, CardType::Ace]), 12);
+    }
+
+    #[test]
+    fn two_aces_and_nine_make_twenty_one() {
+        assert_eq!(score_cards(&[CardType::Ace, CardType::Ace, CardType::Nine]), 21);
+    }
+
+    #[test]
+    fn ace_and_ten_make_twenty_one() {
+        assert_eq!(score_cards(&[CardType::Ace, CardType::Ten]), 21);
+    }
+
+    #[test]
+    fn ace_drops_to_one_instead_of_busting() {
+        assert_eq!(score_cards(&[CardType::Ace, CardType::Six, CardType::Ten]), 17);
+    }
+
+    #[test]
+    fn player_bust_loses_regardless_of_dealer() {
+        assert_eq!(resolve_round(24, 16, false, false, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Casino, player_total: 24, dealer_total: 16, reason: RoundReason::PlayerBust, bet: 10, payout: 0
+        });
+    }
+
+    #[test]
+    fn dealer_bust_pays_player() {
+        assert_eq!(resolve_round(15, 23, false, false, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Player, player_total: 15, dealer_total: 23, reason: RoundReason::DealerBust, bet: 10, payout: 20
+        });
+    }
+
+    #[test]
+    fn higher_total_wins() {
+        assert_eq!(resolve_round(20, 18, false, false, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Player, player_total: 20, dealer_total: 18, reason: RoundReason::PlayerHigher, bet: 10, payout: 20
+        });
+        assert_eq!(resolve_round(18, 20, false, false, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Casino, player_total: 18, dealer_total: 20, reason: RoundReason::DealerHigher, bet: 10, payout: 0
+        });
+    }
+
+    #[test]
+    fn tie_is_a_push_by_default() {
+        assert_eq!(resolve_round(19, 19, false, false, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Tie, player_total: 19, dealer_total: 19, reason: RoundReason::Push, bet: 10, payout: 10
+        });
+        assert_eq!(resolve_round(21, 21, true, true, 10, &RulesConfig::default()).winner, Winner::Tie);
+    }
+
+    #[test]
+    fn dealer_wins_ties_takes_regular_pushes() {
+        assert_eq!(resolve_round(17, 17, false, false, 10, &dealer_wins_ties()), RoundResult {
+            winner: Winner::Casino, player_total: 17, dealer_total: 17, reason: RoundReason::DealerWinsTie, bet: 10, payout: 0
+        });
+        assert_eq!(resolve_round(21, 21, false, false, 10, &dealer_wins_ties()).winner, Winner::Casino);
+    }
+
+    #[test]
+    fn dealer_wins_ties_still_pushes_two_naturals() {
+        assert_eq!(resolve_round(21, 21, true, true, 10, &dealer_wins_ties()).reason, RoundReason::Push);
+    }
+
+    #[test]
+    fn dealer_wins_ties_does_not_change_decided_hands() {
+        assert_eq!(resolve_round(20, 18, false, false, 10, &dealer_wins_ties()).winner, Winner::Player);
+        assert_eq!(resolve_round(18, 20, false, false, 10, &dealer_wins_ties()).winner, Winner::Casino);
+        assert_eq!(resolve_round(18, 22, false, false, 10, &dealer_wins_ties()).winner, Winner::Player);
+    }
+
+    #[test]
+    fn blackjack_winnings_round_down_on_odd_bets() {
+        assert_eq!(blackjack_winnings(10, (3, 2)), 15);
+        assert_eq!(blackjack_winnings(5, (3, 2)), 7);
+        assert_eq!(blackjack_winnings(1, (3, 2)), 1);
+        assert_eq!(blackjack_winnings(25, (6, 5)), 30);
+        assert_eq!(blackjack_winnings(15, (1, 1)), 15);
+    }
+
+    #[test]
+    fn amounts_break_into_the_fewest_chips() {
+        assert_eq!(chips_for_amount(0), vec![]);
+        assert_eq!(chips_for_amount(7), vec![ChipDenom::Five, ChipDenom::One, ChipDenom::One]);
+        assert_eq!(chips_for_amount(130), vec![ChipDenom::Hundred, ChipDenom::TwentyFive, ChipDenom::Five]);
+        assert_eq!(chips_for_amount(250).len(), 4);
+    }
+
+    #[test]
+    fn five_card_charlie_needs_the_rule_and_a_live_hand() {
+        let charlie = RulesConfig { five_card_charlie: true, ..RulesConfig::default() };
+        assert!(is_five_card_charlie(5, 21, &charlie));
+        assert!(is_five_card_charlie(5, 14, &charlie));
+        assert!(!is_five_card_charlie(5, 22, &charlie));
+        assert!(!is_five_card_charlie(4, 20, &charlie));
+        assert!(!is_five_card_charlie(5, 18, &RulesConfig::default()));
+    }
+
+    #[test]
+    fn player_natural_beats_dealer_twenty_one() {
+        assert_eq!(resolve_round(21, 21, true, false, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Player, player_total: 21, dealer_total: 21, reason: RoundReason::PlayerBlackjack, bet: 10, payout: 25
+        });
+    }
+
+    #[test]
+    fn dealer_natural_beats_player_twenty_one() {
+        assert_eq!(resolve_round(21, 21, false, true, 10, &RulesConfig::default()), RoundResult {
+            winner: Winner::Casino, player_total: 21, dealer_total: 21, reason: RoundReason::DealerBlackjack, bet: 10, payout: 0
+        });
+    }
+
+    #[test]
+    fn two_naturals_push() {
+        assert_eq!(resolve_round(21, 21, true, true, 10, &RulesConfig::default()).reason, RoundReason::Push);
+    }
+
+    #[test]
+    fn surrender_refunds_half_rounded_down() {
+        assert_eq!(surrender_refund(10), 5);
+        assert_eq!(surrender_refund(15), 7);
+        assert_eq!(surrender_refund(1), 0);
+    }
+
+    #[test]
+    fn shoe_is_reshuffled_at_the_cut_card() {
+        assert!(needs_reshuffle(0, 52));
+        assert!(needs_reshuffle(12, 52));
+        assert!(!needs_reshuffle(13, 52));
+        assert!(!needs_reshuffle(52, 52));
+    }
+
+    /// A table under `rules` with a bet placed and the cards dealt from `seed`.
+    fn deal_seed(rules: &RulesConfig, seed: u64) -> Table {
+        let mut table = Table::new(rules.clone(), 100, 10, seed);
+        table.place_bet();
+        table.deal();
+        return table;
+    }
+
+    /// The first seed dealing a table under `rules` that `wanted` accepts,
+    /// with that table; `wanted` may play the hand on before deciding.
+    fn dealt_table(rules: &RulesConfig, wanted: impl Fn(&mut Table) -> bool) -> (u64, Table) {
+        for seed in 0..5000 {
+            let mut table = deal_seed(rules, seed);
+            if wanted(&mut table) {
+                return (seed, table);
+            }
+        }
+
+        panic!("no seed deals the table wanted");
+    }
+
+    fn table_awaiting_decision(rules: RulesConfig) -> Table {
+        return dealt_table(&rules, |table| matches!(table.status, GameStatus::AwaitingPlayerDecision)).1;
+    }
+
+    #[test]
+    fn splits_stop_at_the_hand_cap() {
+        let mut table = table_awaiting_decision(RulesConfig { max_split_hands: 3, ..RulesConfig::default() });
+        for hands in 1..=2 {
+            table.player_hands[table.active_hand].cards = hand_of(&[CardType::Eight, CardType::Eight]);
+            assert!(table.can_split());
+            table.split();
+            assert_eq!(table.player_hands.len(), hands + 1);
+        }
+
+        table.player_hands[table.active_hand].cards = hand_of(&[CardType::Eight, CardType::Eight]);
+        assert!(!table.can_split());
+    }
+
+    #[test]
+    fn split_aces_take_one_card_and_resplit_only_when_allowed() {
+        let mut table = table_awaiting_decision(RulesConfig { max_split_hands: 4, ..RulesConfig::default() });
+        table.player_hands[0].cards = hand_of(&[CardType::Ace, CardType::Ace]);
+        table.split();
+        assert!(table.player_hands.iter().all(|hand| hand.cards.len() == 2 && hand.split_aces));
+        assert!(!matches!(table.status, GameStatus::AwaitingPlayerDecision));
+
+        let mut table = table_awaiting_decision(RulesConfig { max_split_hands: 4, split_aces_one_card: false, ..RulesConfig::default() });
+        table.player_hands[0].cards = hand_of(&[CardType::Ace, CardType::Ace]);
+        table.split();
+        table.active_hand = 0;
+        table.player_hands[0].cards = hand_of(&[CardType::Ace, CardType::Ace]);
+        assert!(table.can_hit());
+        assert!(!table.can_split());
+        table.rules.resplit_aces = true;
+        assert!(table.can_split());
+    }
+
+    #[test]
+    fn dealer_natural_ends_the_round_at_the_deal() {
+        let (_, table) = dealt_table(&RulesConfig::default(),
+            |table| table.casino_hand.is_blackjack() && !matches!(table.casino_hand.cards()[0].card_type, CardType::Ace));
+
+        assert_eq!(table.player_hands[0].cards.len(), 2);
+        match &table.status {
+            GameStatus::GameOver(results) => {
+                let expected = if table.player_hands[0].cards.is_blackjack() { Winner::Tie } else { Winner::Casino };
+                assert_eq!(results[0].winner, expected);
+                assert_eq!(results[0].reason, if expected == Winner::Tie { RoundReason::Push } else { RoundReason::DealerBlackjack });
+            },
+            _ => panic!("round did not end on the dealer's natural"),
+        }
+    }
+
+    #[test]
+    fn without_the_peek_a_dealer_natural_waits_for_the_player() {
+        let no_peek = RulesConfig { dealer_peeks: false, ..RulesConfig::default() };
+        let (seed, mut table) = dealt_table(&no_peek,
+            |table| table.casino_hand.is_blackjack() && !table.player_hands[0].cards.is_blackjack());
+
+        assert!(matches!(deal_seed(&RulesConfig::default(), seed).status, GameStatus::GameOver(_) | GameStatus::OfferingInsurance));
+
+        if let GameStatus::OfferingInsurance = table.status {
+            table.resolve_insurance(true);
+        }
+        assert!(matches!(table.status, GameStatus::AwaitingPlayerDecision));
+        table.stand();
+        table.play_dealer();
+        match &table.status {
+            GameStatus::GameOver(results) => assert_eq!(results[0].reason, RoundReason::DealerBlackjack),
+            _ => panic!("round did not finish"),
+        }
+        // Insurance, if it was offered, covers the lost bet at the end.
+        let expected = if table.insurance_bet > 0 { 100 } else { 90 };
+        assert_eq!(table.bankrolls[0], expected);
+    }
+
+    #[test]
+    fn without_a_hole_card_a_dealer_natural_takes_the_double_too() {
+        let european = RulesConfig { dealing: Dealing::European, ..RulesConfig::default() };
+        let (_, table) = dealt_table(&european, |table| {
+            assert_eq!(table.casino_hand.len(), 1);
+            assert!(table.status.hole_card_hidden() || table.player_hands[0].cards.is_blackjack());
+            if !matches!(table.status, GameStatus::AwaitingPlayerDecision) || table.player_hands[0].cards.score() > 11 {
+                return false;
+            }
+
+            table.double_down();
+            assert!(matches!(table.status, GameStatus::PlayerStopedTakingCards));
+            table.play_dealer();
+            return table.casino_hand.is_blackjack();
+        });
+
+        match &table.status {
+            GameStatus::GameOver(results) => {
+                assert_eq!(results[0].reason, RoundReason::DealerBlackjack);
+                assert_eq!(results[0].bet, 20);
+            },
+            _ => panic!("round did not finish"),
+        }
+        assert_eq!(table.bankrolls[0], 80);
+
+        // A natural waits on the dealer's second card before it's paid.
+        let (_, table) = dealt_table(&european, |table| table.player_hands[0].cards.is_blackjack());
+        assert!(matches!(table.status, GameStatus::GameOver(_)));
+        assert_eq!(table.casino_hand.len(), 2);
+    }
+
+    #[test]
+    fn early_surrender_comes_before_the_dealer_peek() {
+        let early = RulesConfig { surrender_allowed: false, early_surrender: true, ..RulesConfig::default() };
+        let (seed, mut surrendered) = dealt_table(&early,
+            |table| table.casino_hand.is_blackjack() && !table.player_hands[0].cards.is_blackjack());
+        assert!(matches!(surrendered.status, GameStatus::OfferingEarlySurrender));
+
+        // Surrendering keeps half the bet even though the dealer has a natural.
+        surrendered.resolve_early_surrender(true);
+        match &surrendered.status {
+            GameStatus::GameOver(results) => assert_eq!(results[0].reason, RoundReason::Surrender),
+            _ => panic!("round did not end on the surrender"),
+        }
+        assert_eq!(surrendered.bankrolls[0], 95);
+
+        // Playing on runs into the peek, after insurance against an ace.
+        let mut declined = deal_seed(&early, seed);
+        declined.resolve_early_surrender(false);
+        if let GameStatus::OfferingInsurance = declined.status {
+            declined.resolve_insurance(false);
+        }
+        match &declined.status {
+            GameStatus::GameOver(results) => assert_eq!(results[0].reason, RoundReason::DealerBlackjack),
+            _ => panic!("round did not end on the dealer's natural"),
+        }
+        assert_eq!(declined.bankrolls[0], 90);
+    }
+
+    #[test]
+    fn early_and_late_surrender_cannot_both_be_on() {
+        let err = config::Config::parse("[rules]\nearly_surrender = true").err().unwrap();
+        assert!(err.contains("early_surrender"));
+        assert!(config::Config::parse("[rules]\nearly_surrender = true\nsurrender_allowed = false").is_ok());
+    }
+
+    #[test]
+    fn bets_are_clamped_to_the_table_limits() {
+        assert_eq!(clamp_bet(5, 10, 500, 1000), 10);
+        assert_eq!(clamp_bet(0, 10, 500, 1000), 10);
+        assert_eq!(clamp_bet(900, 10, 500, 1000), 500);
+        assert_eq!(clamp_bet(400, 10, 500, 300), 300);
+        assert_eq!(clamp_bet(120, 10, 500, 1000), 120);
+
+        let mut table = Table::new(RulesConfig::default(), 1000, 5, 1);
+        assert!(!table.can_place_bet(10, 500));
+        table.current_bet = 600;
+        assert!(!table.can_place_bet(10, 500));
+        table.current_bet = 500;
+        assert!(table.can_place_bet(10, 500));
+    }
+
+    #[test]
+    fn bankrupt_once_the_minimum_bet_cannot_be_covered() {
+        let mut table = Table::new(RulesConfig::default(), 5, 10, 1);
+        assert!(table.is_bankrupt(10));
+        assert!(!table.is_bankrupt(5));
+
+        table.restart(100);
+        assert!(!table.is_bankrupt(10));
+        assert_eq!(table.peak_bankroll, 100);
+        assert_eq!(table.hands_played, 0);
+    }
+
+    #[test]
+    fn dealer_draws_past_player_standing_on_fourteen() {
+        // The player stood on 14; the dealer starts on 12 and passes 14 at 15.
+        let mut dealer = vec![CardType::Ten, CardType::Two];
+        let mut shoe = vec![CardType::Three, CardType::Two, CardType::Nine].into_iter();
+        while dealer_should_hit(&dealer, &RulesConfig::default()) {
+            dealer.push(shoe.next().unwrap());
+        }
+
+        assert_eq!(dealer.len(), 4);
+        assert_eq!(score_cards(&dealer), 17);
+    }
+
+    #[test]
+    fn dealer_stands_from_seventeen() {
+        assert!(dealer_should_hit(&[CardType::Ten, CardType::Six], &RulesConfig::default()));
+        assert!(!dealer_should_hit(&[CardType::Ten, CardType::Seven], &RulesConfig::default()));
+        assert!(!dealer_should_hit(&[CardType::Ten, CardType::Six, CardType::Eight], &RulesConfig::default()));
+    }
+
+    #[test]
+    fn soft_total_is_reported() {
+        let soft = |cards: &[CardType]| {
+            let hand = evaluate_hand(cards);
+            return (hand.total, hand.soft);
+        };
+        assert_eq!(soft(&[CardType::Ace, CardType::Six]), (17, true));
+        assert_eq!(soft(&[CardType::Ace, CardType::Six, CardType::Ten]), (17, false));
+        assert_eq!(soft(&[CardType::Ten, CardType::Seven]), (17, false));
+    }
+
+    fn hand_value(total: usize, soft: bool, bust: bool, blackjack: bool) -> HandValue {
+        return HandValue { total: total, soft: soft, bust: bust, blackjack: blackjack };
+    }
+
+    #[test]
+    fn evaluates_plain_hands() {
+        assert_eq!(evaluate_hand(&[]), hand_value(0, false, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Two, CardType::Three]), hand_value(5, false, false, false));
+        assert_eq!(evaluate_hand(&[CardType::King, CardType::Queen]), hand_value(20, false, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ten, CardType::Six, CardType::Nine]), hand_value(25, false, true, false));
+    }
+
+    #[test]
+    fn evaluates_aces() {
+        assert_eq!(evaluate_hand(&[CardType::Ace]), hand_value(11, true, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Ace]), hand_value(12, true, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Ace, CardType::Ace]), hand_value(13, true, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Ace, CardType::Ace, CardType::Ace, CardType::Seven]),
+            hand_value(21, true, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Ace, CardType::Ten]), hand_value(12, false, false, false));
+    }
+
+    #[test]
+    fn soft_hands_turn_hard_instead_of_busting() {
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Five]), hand_value(16, true, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Five, CardType::Eight]), hand_value(14, false, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Five, CardType::Eight, CardType::Nine]), hand_value(23, false, true, false));
+    }
+
+    #[test]
+    fn only_two_card_twenty_one_is_blackjack() {
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::King]), hand_value(21, true, false, true));
+        assert_eq!(evaluate_hand(&[CardType::Jack, CardType::Ace]), hand_value(21, true, false, true));
+        assert_eq!(evaluate_hand(&[CardType::Seven, CardType::Seven, CardType::Seven]), hand_value(21, false, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Five, CardType::Five]), hand_value(21, true, false, false));
+        assert_eq!(evaluate_hand(&[CardType::Ace, CardType::Ten, CardType::Ten]), hand_value(21, false, false, false));
+    }
+
+    fn hand_of(card_types: &[CardType]) -> Hand {
+        let mut hand = Hand::new();
+        for (id, card_type) in card_types.iter().enumerate() {
+            hand.push(Card { id: id, card_type: *card_type, card_suit: CardSuit::Spades, path: String::new() });
+        }
+
+        return hand;
+    }
+
+    #[test]
+    fn hand_reports_its_own_value() {
+        let hand = hand_of(&[CardType::Ace, CardType::King]);
+        assert_eq!(hand.score(), 21);
+        assert!(hand.is_blackjack() && hand.is_soft() && !hand.is_bust());
+
+        let mut hand = hand_of(&[CardType::Ten, CardType::Six]);
+        assert!(hand.can_bust());
+        hand.push(Card { id: 2, card_type: CardType::Nine, card_suit: CardSuit::Hearts, path: String::new() });
+        assert!(hand.is_bust() && !hand.is_blackjack());
+        assert!(hand.contains(2) && !hand.contains(3));
+
+        assert!(!hand_of(&[CardType::Ace, CardType::Five]).can_bust());
+    }
+
+    #[test]
+    fn only_a_hand_counting_an_ace_as_11_is_labelled_soft() {
+        assert_eq!(hand_of(&[CardType::Ace, CardType::Six]).value().label(), "Soft 17");
+        assert_eq!(hand_of(&[CardType::Ten, CardType::Seven]).value().label(), "17");
+        assert_eq!(hand_of(&[CardType::Ace, CardType::Six, CardType::Ten]).value().label(), "17");
+        assert_eq!(hand_of(&[CardType::Ace, CardType::Ace, CardType::Ten, CardType::Nine]).value().label(), "21");
+    }
+
+    #[test]
+    fn dealer_hits_soft_17_only_when_enabled() {
+        let hits_soft_17 = RulesConfig { dealer_hits_soft_17: true, ..RulesConfig::default() };
+        let soft_17 = [CardType::Ace, CardType::Six];
+        let hard_17 = [CardType::Ten, CardType::Seven];
+
+        assert!(!dealer_should_hit(&soft_17, &RulesConfig::default()));
+        assert!(dealer_should_hit(&soft_17, &hits_soft_17));
+        assert!(!dealer_should_hit(&hard_17, &hits_soft_17));
+        assert!(!dealer_should_hit(&[CardType::Ace, CardType::Seven], &hits_soft_17));
+    }
+
+    #[test]
+    fn double_down_takes_one_card_then_stands() {
+        assert_eq!(hand_progress(15, true), HandProgress::Stand);
+        assert_eq!(hand_progress(15, false), HandProgress::Continue);
+        assert_eq!(hand_progress(24, true), HandProgress::Bust);
+    }
+
+    #[test]
+    fn double_down_only_on_opening_hand() {
+        assert!(can_double_down(2, 100, 50));
+        assert!(!can_double_down(3, 100, 50));
+        assert!(!can_double_down(2, 40, 50));
+    }
+
+    #[test]
+    fn strategy_splits_aces_and_eights_but_never_tens() {
+        assert_eq!(basic_strategy(&[CardType::Ace, CardType::Ace], CardType::Ten), Action::Split);
+        assert_eq!(basic_strategy(&[CardType::Eight, CardType::Eight], CardType::Ace), Action::Split);
+        assert_eq!(basic_strategy(&[CardType::King, CardType::King], CardType::Six), Action::Stand);
+        assert_eq!(basic_strategy(&[CardType::Five, CardType::Five], CardType::Six), Action::DoubleDown);
+    }
+
+    #[test]
+    fn strategy_plays_soft_totals() {
+        assert_eq!(basic_strategy(&[CardType::Ace, CardType::Seven], CardType::Four), Action::DoubleDown);
+        assert_eq!(basic_strategy(&[CardType::Ace, CardType::Seven], CardType::Eight), Action::Stand);
+        assert_eq!(basic_strategy(&[CardType::Ace, CardType::Seven], CardType::Ten), Action::Hit);
+        assert_eq!(basic_strategy(&[CardType::Ace, CardType::Two, CardType::Five], CardType::Four), Action::Stand);
+    }
+
+    #[test]
+    fn strategy_plays_hard_totals() {
+        assert_eq!(basic_strategy(&[CardType::Six, CardType::Five], CardType::Ten), Action::DoubleDown);
+        assert_eq!(basic_strategy(&[CardType::Ten, CardType::Two], CardType::Three), Action::Hit);
+        assert_eq!(basic_strategy(&[CardType::Ten, CardType::Six], CardType::Six), Action::Stand);
+        assert_eq!(basic_strategy(&[CardType::Ten, CardType::Six], CardType::Seven), Action::Hit);
+        assert_eq!(basic_strategy(&[CardType::Four, CardType::Three, CardType::Four], CardType::Six), Action::Hit);
+    }
+
+    #[test]
+    fn strategy_plays_the_total_when_it_cannot_split_or_double() {
+        let eights = [CardType::Eight, CardType::Eight];
+        assert_eq!(basic_strategy_allowing(&eights, CardType::Six, true, false), Action::Stand);
+        assert_eq!(basic_strategy_allowing(&eights, CardType::Ten, true, false), Action::Hit);
+        assert_eq!(basic_strategy_allowing(&[CardType::Seven, CardType::Seven], CardType::Four, false, false), Action::Stand);
+        assert_eq!(basic_strategy_allowing(&[CardType::Ace, CardType::Seven], CardType::Four, false, true), Action::Stand);
+    }
+
+    #[test]
+    fn hi_lo_counts_low_cards_up_and_high_cards_down() {
+        assert_eq!(hi_lo_value(CardType::Two), 1);
+        assert_eq!(hi_lo_value(CardType::Six), 1);
+        assert_eq!(hi_lo_value(CardType::Eight), 0);
+        assert_eq!(hi_lo_value(CardType::Queen), -1);
+        assert_eq!(hi_lo_value(CardType::Ace), -1);
+    }
+
+    #[test]
+    fn simulation_is_reproducible_from_its_seed() {
+        let run = || simulate(RulesConfig::default(), 10, 2000, 99, suggest);
+        let report = run();
+
+        assert_eq!(report, run());
+        assert_eq!(report.player_wins + report.casino_wins + report.ties, 2000);
+        assert_eq!(report.trajectory.len(), 10);
+        assert!(report.house_edge().abs() < 0.1);
+    }
+
+    #[test]
+    fn achievements_follow_the_round_that_just_finished() {
+        let result = |winner: Winner, reason: RoundReason| RoundResult {
+            winner: winner, player_total: 21, dealer_total: 18, reason: reason, bet: 10, payout: settle_bet(winner, 10)
+        };
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 1);
+        let mut progress = AchievementProgress::default();
+
+        table.status = GameStatus::GameOver(vec![result(Winner::Player, RoundReason::PlayerBlackjack)]);
+        assert_eq!(progress.record_round(&table, 100), vec![Achievement::FirstBlackjack]);
+
+        table.status = GameStatus::GameOver(vec![result(Winner::Player, RoundReason::PlayerHigher)]);
+        for _ in 0..3 {
+            assert!(progress.record_round(&table, 100).is_empty());
+        }
+        table.status = GameStatus::GameOver(vec![result(Winner::Tie, RoundReason::Push)]);
+        assert!(progress.record_round(&table, 100).is_empty());
+        table.status = GameStatus::GameOver(vec![result(Winner::Player, RoundReason::PlayerHigher)]);
+        assert_eq!(progress.record_round(&table, 100), vec![Achievement::FiveInARow]);
+
+        table.bankrolls[0] = 200;
+        table.status = GameStatus::GameOver(vec![result(Winner::Casino, RoundReason::DealerHigher)]);
+        assert_eq!(progress.record_round(&table, 100), vec![Achievement::DoubleBankroll]);
+        assert_eq!(progress.win_streak, 0);
+    }
+
+    #[test]
+    fn same_seed_deals_same_cards() {
+        let deal = |seed: u64| {
+            let mut table = Table::new(RulesConfig::default(), 100, 10, seed);
+            table.place_bet();
+            table.deal();
+            let ids = |hand: &Hand| hand.cards().iter().map(|card| card.id).collect::<Vec<usize>>();
+            return (ids(&table.player_hands[0].cards), ids(&table.casino_hand));
+        };
+
+        assert_eq!(deal(42), deal(42));
+    }
+
+    #[test]
+    fn dealer_total_hides_the_hole_card_until_the_player_stands() {
+        let mut table = (0..100)
+            .map(|seed| {
+                let mut table = Table::new(RulesConfig::default(), 100, 10, seed);
+                table.place_bet();
+                table.deal();
+                return table;
+            })
+            .find(|table| matches!(table.status, GameStatus::AwaitingPlayerDecision))
+            .unwrap();
+
+        assert_eq!(table.dealer_visible_score(), score_cards(&table.casino_hand.card_types()[..1]));
+
+        table.stand();
+        assert!(matches!(table.status, GameStatus::PlayerStopedTakingCards));
+        assert_eq!(table.dealer_visible_score(), table.casino_hand.score());
+    }
+
+    #[test]
+    fn even_money_is_offered_on_a_natural_against_an_ace_and_pays_one_to_one() {
+        let (_, mut table) = dealt_table(&RulesConfig::default(), |table| matches!(table.status, GameStatus::OfferingEvenMoney));
+
+        assert!(matches!(table.casino_hand.cards()[0].card_type, CardType::Ace));
+        table.resolve_even_money(true);
+        match &table.status {
+            GameStatus::GameOver(results) => assert_eq!(results[0].reason, RoundReason::EvenMoney),
+            _ => panic!("even money did not settle the round"),
+        }
+        assert_eq!(table.bankrolls[0], 110);
+    }
+
+    #[test]
+    fn each_player_plays_and_settles_their_own_hand() {
+        let mut table = Table::new(RulesConfig { players: 3, ..RulesConfig::default() }, 100, 10, 11);
+        table.bankrolls[2] = 5;
+        table.place_bet();
+        table.deal();
+
+        assert_eq!(table.bankrolls, vec![90, 90, 5]);
+        assert_eq!(table.player_hands.iter().map(|hand| hand.seat).collect::<Vec<usize>>(), vec![0, 1]);
+
+        while let GameStatus::AwaitingPlayerDecision = table.status {
+            table.stand();
+        }
+        if let GameStatus::PlayerStopedTakingCards = table.status {
+            table.play_dealer();
+        }
+
+        match &table.status {
+            GameStatus::GameOver(results) => {
+                assert_eq!(results.len(), 2);
+                assert_eq!(table.bankrolls, vec![90 + results[0].payout, 90 + results[1].payout, 5]);
+            },
+            _ => panic!("round did not finish"),
+        }
+    }
+
+    #[test]
+    fn shoe_starts_full_and_shrinks_as_cards_are_dealt() {
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 3);
+        assert_eq!(table.cards_remaining(), 52);
+
+        table.place_bet();
+        table.deal();
+        assert_eq!(table.cards_remaining(), 48);
+    }
+
+    #[test]
+    fn redeal_returns_every_bet_and_deals_again() {
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 3);
+        table.place_bet();
+        table.deal();
+        assert_eq!(table.bankrolls[0], 90);
+
+        table.redeal(false);
+        assert!(matches!(table.status, GameStatus::Uninitialized));
+        assert_eq!(table.bankrolls[0], 90);
+        assert_eq!(table.hands_played, 0);
+        assert_eq!(table.cards_discarded(), 4);
+
+        table.redeal(true);
+        assert_eq!(table.bankrolls[0], 90);
+        assert_eq!(table.cards_remaining(), 52);
+        assert_eq!(table.running_count, 0);
+    }
+
+    #[test]
+    fn excluded_ranks_are_left_out_of_the_shoe() {
+        let spanish = build_deck(&[CardType::Ten], 6);
+        assert_eq!(spanish.len(), 6 * 48);
+        assert!(spanish.iter().all(|card| card.card_type != CardType::Ten));
+        assert_eq!(spanish.iter().filter(|card| card.card_type == CardType::Jack).count(), 6 * 4);
+        assert!(spanish.iter().enumerate().all(|(idx, card)| card.id == idx));
+
+        assert_eq!(build_deck(&[], 2).len(), get_deck(2).len());
+        assert_eq!(build_deck(&[CardType::Two, CardType::Three], 1).len(), 44);
+    }
+
+    #[test]
+    fn missing_card_images_are_each_listed_once() {
+        assert!(missing_card_assets(&get_deck(2)).is_empty());
+
+        let mut deck = get_deck(1);
+        deck[3].path = "assets/cards/missing.png".to_string();
+        deck[7].path = "assets/cards/missing.png".to_string();
+        deck[9].path = "assets/cards/gone.png".to_string();
+        assert_eq!(missing_card_assets(&deck), vec!["assets/cards/missing.png", "assets/cards/gone.png"]);
+    }
+
+    #[test]
+    fn discards_build_up_until_the_reshuffle() {
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 3);
+        table.place_bet();
+        table.deal();
+        assert_eq!(table.cards_discarded(), 0);
+
+        table.clear_round();
+        assert_eq!(table.cards_discarded(), 4);
+
+        table.reshuffle();
+        assert_eq!(table.cards_discarded(), 0);
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_session() {
+        // Deals and the dealer's draws need no input, as in the game loop.
+        let settle = |table: &mut Table| {
+            if let GameStatus::Shuffling = table.status {
+                table.reshuffle();
+                table.deal();
+            }
+            if let GameStatus::Uninitialized = table.status {
+                table.deal();
+            }
+            if let GameStatus::PlayerStopedTakingCards = table.status {
+                table.play_dealer();
+            }
+        };
+
+        let mut replay = Replay::new(5, 100, RulesConfig::default());
+        let mut table = replay.table();
+        for round in 0..20 {
+            let mut next = Some(ReplayEvent::Bet(10));
+            while let Some(event) = next {
+                event.apply(&mut table);
+                settle(&mut table);
+                replay.events.push(event);
+
+                next = match table.status {
+                    GameStatus::OfferingInsurance => Some(ReplayEvent::Insurance(round % 2 == 0)),
+                    GameStatus::OfferingEvenMoney => Some(ReplayEvent::EvenMoney(false)),
+                    GameStatus::AwaitingPlayerDecision if table.player_hands[table.active_hand].cards.score() < 15 => Some(ReplayEvent::Hit),
+                    GameStatus::AwaitingPlayerDecision => Some(ReplayEvent::Stand),
+                    _ => None,
+                };
+            }
+            ReplayEvent::NextRound.apply(&mut table);
+            replay.events.push(ReplayEvent::NextRound);
+        }
+
+        let path = std::env::temp_dir().join("blackjack_replay_test.toml");
+        let path = path.to_str().unwrap();
+        replay.save(path).unwrap();
+        let loaded = Replay::load(path).unwrap();
+        assert_eq!(loaded.events, replay.events);
+
+        let mut replayed = loaded.table();
+        for event in &loaded.events {
+            event.apply(&mut replayed);
+            settle(&mut replayed);
+        }
+        assert_eq!(replayed.bankrolls, table.bankrolls);
+    }
+
+    #[test]
+    fn betting_past_the_cut_card_shuffles_before_the_deal() {
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 4);
+        table.shoe.truncate(10);
+        table.running_count = 3;
+        table.place_bet();
+        assert!(matches!(table.status, GameStatus::Shuffling));
+
+        table.reshuffle();
+        assert_eq!(table.cards_remaining(), 52);
+        assert_eq!(table.running_count, 0);
+    }
+
+    #[test]
+    fn a_short_shoe_is_reshuffled_before_the_opening_deal() {
+        let mut table = Table::new(RulesConfig { players: 4, ..RulesConfig::default() }, 100, 10, 8);
+        table.shoe.truncate(3);
+        table.place_bet();
+        table.deal();
+
+        assert!(table.player_hands.iter().all(|hand| hand.cards.len() == 2));
+        assert_eq!(table.casino_hand.len(), 2);
+        assert_eq!(table.cards_remaining(), 52 - 10);
+    }
+
+    #[test]
+    fn round_plays_out_without_a_window() {
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 7);
+        table.place_bet();
+        table.deal();
+        if let GameStatus::OfferingInsurance = table.status {
+            table.resolve_insurance(false);
+        }
+        if let GameStatus::AwaitingPlayerDecision = table.status {
+            table.stand();
+        }
+        if let GameStatus::PlayerStopedTakingCards = table.status {
+            table.play_dealer();
+        }
+
+        match &table.status {
+            GameStatus::GameOver(results) => assert_eq!(table.bankrolls[0], 90 + results[0].payout),
+            _ => panic!("round did not finish"),
+        }
+    }
+
+    #[test]
+    fn dealer_steps_draw_one_card_at_a_time() {
+        let stood = |seed: u64| {
+            let mut table = Table::new(RulesConfig::default(), 100, 10, seed);
+            table.place_bet();
+            table.deal();
+            if let GameStatus::OfferingInsurance = table.status {
+                table.resolve_insurance(false);
+            }
+            if let GameStatus::AwaitingPlayerDecision = table.status {
+                table.stand();
+            }
+            return table;
+        };
+        let seed = (0..500)
+            .find(|seed| {
+                let mut table = stood(*seed);
+                table.play_dealer();
+                return table.casino_hand.len() > 3;
+            })
+            .unwrap();
+
+        let mut played = stood(seed);
+        played.play_dealer();
+        let mut stepped = stood(seed);
+        let mut cards = stepped.casino_hand.len();
+        while let GameStatus::PlayerStopedTakingCards = stepped.status {
+            stepped.dealer_step();
+            assert!(stepped.casino_hand.len() <= cards + 1);
+            cards = stepped.casino_hand.len();
+        }
+
+        assert_eq!(stepped.casino_hand.len(), played.casino_hand.len());
+        assert_eq!(stepped.bankrolls, played.bankrolls);
+        assert_eq!(stepped.history, played.history);
+    }
+
+    #[test]
+    fn bust_chance_counts_the_unseen_cards() {
+        let mut table = Table::new(RulesConfig::default(), 100, 10, 2);
+        table.place_bet();
+        table.deal();
+        table.status = GameStatus::AwaitingPlayerDecision;
+
+        let card = |id: usize, card_type: CardType| Card { id: id, card_type: card_type, card_suit: CardSuit::Clubs, path: String::new() };
+        table.player_hands[0].cards = hand_of(&[CardType::Ten, CardType::Six]);
+        table.shoe = vec![0, 1, 2, 3];
+        table.deck = vec![
+            card(0, CardType::Five), card(1, CardType::Six), card(2, CardType::King), card(3, CardType::Ace),
+            card(4, CardType::Two), card(5, CardType::Seven),
+        ];
+        // The hole card is still unseen, so it counts alongside the shoe.
+        table.casino_hand = Hand::new();
+        table.casino_hand.push(card(4, CardType::Two));
+        table.casino_hand.push(card(5, CardType::Seven));
+        assert_eq!(table.bust_probability(), 3.0 / 5.0);
+
+        table.player_hands[0].cards = hand_of(&[CardType::Ace, CardType::Five]);
+        assert_eq!(table.bust_probability(), 0.0);
+    }
+
+    #[test]
+    fn a_saved_table_carries_on_dealing_the_same_cards() {
+        // Plays until the table next waits on the player, standing on 17.
+        let play = |table: &mut Table| {
+            loop {
+                match table.status {
+                    GameStatus::Shuffling | GameStatus::Uninitialized => table.deal(),
+                    GameStatus::OfferingInsurance => table.resolve_insurance(false),
+                    GameStatus::OfferingEvenMoney => table.resolve_even_money(false),
+                    GameStatus::AwaitingPlayerDecision if table.player_hands[table.active_hand].cards.score() < 17 => table.hit(),
+                    GameStatus::AwaitingPlayerDecision => table.stand(),
+                    GameStatus::PlayerStopedTakingCards => table.play_dealer(),
+                    _ => return,
+                }
+            }
+        };
+
+        let mut table = Table::new(RulesConfig { decks: 2, ..RulesConfig::default() }, 1000, 10, 11);
+        table.clear_round();
+        table.place_bet();
+        table.deal();
+
+        let path = std::env::temp_dir().join("blackjack_save_test.toml");
+        let path = path.to_str().unwrap();
+        table.save(path).unwrap();
+        let mut loaded = Table::load(path).unwrap();
+        assert_eq!(loaded.cards_remaining(), table.cards_remaining());
+        assert_eq!(loaded.player_hands[0].cards.score(), table.player_hands[0].cards.score());
+
+        for _ in 0..40 {
+            play(&mut table);
+            play(&mut loaded);
+            assert_eq!(loaded.history, table.history);
+            table.clear_round();
+            table.place_bet();
+            loaded.clear_round();
+            loaded.place_bet();
+        }
+        assert_eq!(loaded.bankrolls, table.bankrolls);
+        assert_eq!(loaded.running_count, table.running_count);
+
+        // A settled round saves as well.
+        play(&mut table);
+        table.save(path).unwrap();
+        assert!(matches!(Table::load(path).unwrap().status, GameStatus::GameOver(_)));
+    }
+
+    #[test]
+    fn two_actions_cannot_share_a_key() {
+        assert!(config::Config::parse("[keys]\nhit = \"G\"\nstand = \"J\"").is_ok());
+
+        let err = config::Config::parse("[keys]\nsplit = \"f\"").err().unwrap();
+        assert_eq!(err, "keys.hit and keys.split are both bound to F");
+
+        let err = config::Config::parse("[keys]\nhit = \"s\"").err().unwrap();
+        assert_eq!(err, "keys.hit and surrender are both bound to S");
+        let err = config::Config::parse("[keys]\nrestart = \"escape\"").err().unwrap();
+        assert_eq!(err, "keys.restart and pause are both bound to Escape");
+    }
+}
@@ -1,423 +1,2087 @@
 use sdl2::pixels::Color;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 use sdl2::rect::Rect;
-use sdl2::render::{Texture, TextureCreator, Canvas};
-use sdl2::surface::Surface;
-use sdl2::ttf::Sdl2TtfContext;
+use sdl2::render::{BlendMode, Texture, TextureCreator, Canvas};
+use sdl2::ttf::Font;
 use sdl2::video::{WindowContext, Window};
 use std::collections::HashMap;
 use std::rc::Rc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::path::Path;
 use sdl2::image::LoadTexture;
-use rand::Rng;
+use log::{debug, info, warn};
+use blackjack::achievements::{Achievement, AchievementProgress};
+use blackjack::cache::LruCache;
+use blackjack::leaderboard::{Leaderboard, LEADERBOARD_PATH};
+use blackjack::config::{save_settings, Config, Dealing, EscapeAction, OutcomeStyle, CONFIG_PATH, MAX_DECKS, MAX_SPLIT_HANDS};
+use blackjack::replay::{Replay, ReplayEvent};
+use blackjack::stats::{Stats, STATS_PATH};
+use blackjack::simulation::simulate;
+use blackjack::strategy::{playable_action, suggest};
+use blackjack::{chips_for_amount, clamp_bet, get_deck, insurance_stake, missing_card_assets, overall_winner, Action, GameStatus, RoundReason, RoundResult, Table, Winner, ChipDenom, SAVE_PATH};
 
-const WIDTH: u32 = 1200;
-const HEIGHT: u32 = 1000;
+mod keys;
+mod quips;
+mod sounds;
 
-const TWENTY_ONE: usize = 21;
-const CASINO_STOP_SCORE: usize = 17;
+use keys::KeyBindings;
+use quips::{QuipSituation, Quips};
+use sounds::{Sound, Sounds};
 
 const WIN_NAME: &str = "BlackJack";
+const USAGE: &str = "usage: blackjack [--seed <n>] [--decks <n>] [--bankroll <n>] [--players <n>] [--fullscreen] [--mute] [--demo] [--debug] [--simulate <rounds>] [--record <file>] [--replay <file>]";
+const MAX_FPS: u32 = 60;
 
-const TAKE_ANOTHER_CARD_TEXT: &str = "Press F to take another card";
-const STOP_TAKING_CARDS_TEXT: &str = "Press E to stay with cards currently in hand";
+const FONT_PATH: &str = "./assets/fonts/opensans/OpenSans-Regular.ttf";
+/// Narrowest board the layout is drawn for; narrower windows letterbox.
+const MIN_LAYOUT_WIDTH: u32 = 640;
+/// Runtime-rendered strings kept before the least recently used is dropped.
+const TEXT_CACHE_CAPACITY: usize = 256;
+const CARD_WIDTH: u32 = 100;
+const CARD_HEIGHT: u32 = 150;
+/// Enough of an overlapped card to still read its rank.
+const MIN_CARD_STEP: i32 = 25;
+const DEAL_ANIMATION_SECONDS: f32 = 0.3;
+const HOLE_CARD_FLIP_SECONDS: f32 = 0.25;
+/// Pause before each of the dealer's moves, so their play can be followed.
+const DEALER_DRAW_SECONDS: f32 = 0.5;
+const BUST_FLASH_SECONDS: f32 = 0.6;
+const BUST_FLASH_COLOR: Color = Color::RGBA(220, 30, 30, 170);
+const SHUFFLE_SECONDS: f32 = 1.5;
+const SHUFFLING_TEXT: &str = "Shuffling...";
+const ACTIVE_HAND_COLOR: Color = Color::RGB(255, 215, 0);
+const WINNER_COLOR: Color = Color::RGB(80, 220, 100);
+const CHIP_SIZE: u32 = 40;
+/// How far each chip sits above the one below it in a stack.
+const CHIP_STEP: i32 = 6;
+/// Taller stacks are cut off; the numbers above still give the exact amount.
+const MAX_CHIPS_PER_STACK: usize = 12;
+const CARD_BACK_PATH: &str = "assets/cards/card_back.png";
+const BET_CIRCLE_PATH: &str = "assets/table/bet_circle.png";
+/// How far the betting circle reaches past the cards on each side.
+const BET_CIRCLE_MARGIN: u32 = 15;
+
+// Prompts for rebindable keys; `key_prompt` puts the bound key in front.
+const TAKE_ANOTHER_CARD_TEXT: &str = "to take another card";
+const STOP_TAKING_CARDS_TEXT: &str = "to stay with cards currently in hand";
+const DOUBLE_DOWN_TEXT: &str = "to double down";
+const DECLINE_INSURANCE_TEXT: &str = "to play on without it";
+const TAKE_EVEN_MONEY_TEXT: &str = "Blackjack! Press Y to take even money now";
+const DECLINE_EVEN_MONEY_TEXT: &str = "Press N to play on for 3:2 against the dealer's ace";
+const SURRENDER_TEXT: &str = "Press S to surrender";
+const EARLY_SURRENDER_TEXT: &str = "Press S to surrender before the dealer checks";
+const DECLINE_EARLY_SURRENDER_TEXT: &str = "to keep the hand";
+const YOU_SURRENDERED_TEXT: &str = "You surrendered";
+const SPLIT_TEXT: &str = "to split";
+const HIT_BUTTON_TEXT: &str = "Hit";
+const STAND_BUTTON_TEXT: &str = "Stand";
+const DOUBLE_BUTTON_TEXT: &str = "Double";
+const SPLIT_BUTTON_TEXT: &str = "Split";
+const SAFE_TO_HIT_TEXT: &str = "You can't bust on the next card";
 
 const PLAYER_WINS_TEXT: &str = "Player wins!";
+const PLAYER_BLACKJACK_TEXT: &str = "Blackjack!";
+const FIVE_CARD_CHARLIE_TEXT: &str = "Five-card Charlie!";
+const EVEN_MONEY_TEXT: &str = "Even money paid";
 const CASINO_WINS_TEXT: &str = "Casino wins!";
 const ITS_A_TIE_TEXT: &str = "It's a tie!";
-const N_TO_RESTART_THE_GAME: &str = "Press N to restart the game";
-
-#[derive(Clone, Copy)]
-enum CardType {
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace
-}
-
-impl CardType {
-    fn iterator() -> impl Iterator<Item = CardType> {
-        return [CardType::Two,
-        CardType::Three,
-        CardType::Four,
-        CardType::Five,
-        CardType::Six,
-        CardType::Seven,
-        CardType::Eight,
-        CardType::Nine,
-        CardType::Ten,
-        CardType::Jack,
-        CardType::Queen,
-        CardType::King,
-        CardType::Ace].iter().copied();
-    }
-
-    fn get_score(&self) -> usize {
-        return match self {
-            CardType::Two => 2,
-            CardType::Three => 3,
-            CardType::Four => 4,
-            CardType::Five => 5,
-            CardType::Six => 6,
-            CardType::Seven => 7,
-            CardType::Eight => 8,
-            CardType::Nine => 9,
-            CardType::Ten => 10,
-            CardType::Jack | CardType::Queen | CardType::King => 10,
-            CardType::Ace => 11,
-        } 
-    }
-
-    fn get_string_name(&self) -> String {
-        return match self {
-            CardType::Two => "2".to_string(),
-            CardType::Three => "3".to_string(),
-            CardType::Four => "4".to_string(),
-            CardType::Five => "5".to_string(),
-            CardType::Six => "6".to_string(),
-            CardType::Seven => "7".to_string(),
-            CardType::Eight => "8".to_string(),
-            CardType::Nine => "9".to_string(),
-            CardType::Ten => "10".to_string(),
-            CardType::Jack => "jack".to_string(),
-            CardType::Queen => "queen".to_string(), 
-            CardType::King => "king".to_string(),
-            CardType::Ace => "ace".to_string(),
-        } 
-    }
-}
+const RESTART_THE_GAME_TEXT: &str = "to restart the game, R to rebet or M for the menu";
+const OUT_OF_CHIPS_TEXT: &str = "You're out of chips";
+const START_OVER_OR_QUIT_TEXT: &str = "to start over with a fresh bankroll or Q to quit";
+const SEE_RESULTS_TEXT: &str = "to see how you finished";
+const TOURNAMENT_OVER_TEXT: &str = "Tournament over";
+const ENTER_INITIALS_TEXT: &str = "New high score! Type your initials and press Enter";
+const MENU_ITEMS: [&str; 6] = ["Start", "Tournament", "Settings", "Achievements", "Stats", "Quit"];
+const MENU_START: usize = 0;
+const MENU_TOURNAMENT: usize = 1;
+const MENU_SETTINGS: usize = 2;
+const MENU_ACHIEVEMENTS: usize = 3;
+const MENU_STATS: usize = 4;
+/// Hands a tournament lasts; the bankroll after the last one is the score.
+const TOURNAMENT_HANDS: u32 = 20;
+const INITIALS_LENGTH: usize = 3;
+const MENU_HELP_TEXT: &str = "Use Up/Down to choose and Enter to select";
+const ENTER_TO_GO_BACK_TEXT: &str = "Press Enter to go back";
+const SETTINGS_HELP_TEXT: &str = "Up/Down to choose, Left/Right to change, Enter to save and go back";
+const SETTINGS_COUNT: usize = 13;
+const SUIT_BADGE_SIZE: u32 = 30;
+/// Natural payouts the settings screen cycles through.
+const BLACKJACK_PAYOUTS: [(u32, u32); 3] = [(3, 2), (6, 5), (1, 1)];
+const CHOOSE_BET_TEXT: &str = "Use 1-9 or +/- to choose your bet";
+const ENTER_TO_DEAL_TEXT: &str = "Press Enter to deal or R to repeat the last bet";
+const PAUSE_ITEMS: [&str; 5] = ["Resume", "Restart", "Save", "Load", "Quit"];
+const PAUSE_RESUME: usize = 0;
+const PAUSE_RESTART: usize = 1;
+const PAUSE_SAVE: usize = 2;
+const PAUSE_LOAD: usize = 3;
+const PAUSE_HELP_TEXT: &str = "Use Up/Down to choose and Enter to select, Escape to resume";
+const PAUSED_TEXT: &str = "Paused";
+const TOAST_SECONDS: f32 = 2.5;
+/// Most recent lines of the hand history shown at once.
+const HISTORY_LINES: usize = 8;
+const HISTORY_LINE_HEIGHT: u32 = 26;
+const DISCARD_PILE_Y: i32 = 230;
+const DISCARD_STEP: i32 = 2;
+const REPLAY_NEXT_TEXT: &str = "press Space for the next move";
+const DEMO_TEXT: &str = "Basic strategy demo, press A to take over";
+/// Pause between the demo's moves, so they can be followed.
+const DEMO_MOVE_SECONDS: f32 = 0.7;
+/// How much each new frame moves the smoothed frame time shown by the
+/// diagnostics overlay.
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
 
 struct TextureManager<'a> {
-    cache: HashMap<String, Rc<Texture<'a>>>,
-    loader: &'a TextureCreator<WindowContext>
+    cache: LruCache<Rc<Texture<'a>>>,
+    loader: &'a TextureCreator<WindowContext>,
+    font: Font<'a, 'static>,
+    text_color: Color
 }
 
 impl <'a> TextureManager<'a> {
-    fn load_texture(&mut self, path: &str) -> &Rc<Texture> {
-        if  self.cache.contains_key(path) {
-            return &self.cache[path];
-        }
+    /// Card images are loaded once and kept for the whole session.
+    fn load_texture(&mut self, path: &str) -> Result<Rc<Texture<'a>>, String> {
+        let loader = self.loader;
+        return self.cache.get_or_insert_with(path, true, || {
+            let texture = loader.load_texture(path)
+                .map_err(|err| format!("failed to load texture {}: {}", path, err))?;
+            return Ok(Rc::new(texture));
+        });
+    }
 
-        self.cache.insert(path.to_string(), Rc::new(self.loader.load_texture(path).unwrap()));
-        return &self.cache[path];
+    /// Renders `text` with the game font the first time it is asked for and
+    /// caches the texture under the text itself. Meant for the fixed prompts,
+    /// which stay cached for good.
+    fn load_text(&mut self, text: &str) -> Rc<Texture<'a>> {
+        return self.load_text_texture(text, true);
     }
 
-    fn load_texture_from_surface(&mut self, path: &str, surface: Surface) {
-        self.cache.insert(path.to_string(), Rc::new(self.loader.create_texture_from_surface(surface).unwrap()));
+    /// Same as `load_text` for text that changes from round to round, such
+    /// as totals and amounts. These are evicted once too many pile up.
+    fn load_dynamic_text(&mut self, text: &str) -> Rc<Texture<'a>> {
+        return self.load_text_texture(text, false);
     }
 
-    fn new(loader: &'a TextureCreator<WindowContext>) -> TextureManager<'a> {
-        return TextureManager {
-            cache: HashMap::<String, Rc<Texture<'a>>>::new(),
-            loader: loader
-        };
+    fn load_text_texture(&mut self, text: &str, pinned: bool) -> Rc<Texture<'a>> {
+        let (loader, font, text_color) = (self.loader, &self.font, self.text_color);
+        let texture = self.cache.get_or_insert_with(text, pinned, || -> Result<Rc<Texture<'a>>, String> {
+            let surface = font.render(text).blended(text_color).unwrap();
+            return Ok(Rc::new(loader.create_texture_from_surface(surface).unwrap()));
+        });
+
+        return texture.unwrap();
     }
-}
 
-#[derive(Clone, Copy)]
-enum CardSuit {
-    Clubs,
-    Diamonds,
-    Hearts,
-    Spades
-}
+    /// Textures held right now, images and text together.
+    fn cached_count(&self) -> usize {
+        return self.cache.len();
+    }
 
-impl CardSuit {
-    fn iterator() -> impl Iterator<Item = CardSuit> {
-        return [
-            CardSuit::Clubs,
-            CardSuit::Diamonds,
-            CardSuit::Hearts,
-            CardSuit::Spades,
-        ].iter().copied();
-    }
-
-    fn get_string_name(&self) -> String {
-        return match self {
-            CardSuit::Clubs => "clubs".to_string(),
-            CardSuit::Diamonds => "diamonds".to_string(),
-            CardSuit::Hearts => "hearts".to_string(),
-            CardSuit::Spades => "spades".to_string(),
+    fn new(loader: &'a TextureCreator<WindowContext>, font: Font<'a, 'static>, text_color: Color) -> TextureManager<'a> {
+        return TextureManager {
+            cache: LruCache::new(Some(TEXT_CACHE_CAPACITY)),
+            loader: loader,
+            font: font,
+            text_color: text_color
         };
     }
 }
 
-struct Card {
-    card_type: CardType,
-    _card_suit: CardSuit,
-    path: String
+/// A card sliding from the shoe to its place on the table. The target is
+/// worked out when drawing, as the hand's layout can change mid-slide.
+struct CardAnimation {
+    start: (i32, i32),
+    progress: f32
 }
 
-enum Winner {
-    Player,
-    Casino,
-    Tie
-}
-
-enum GameStatus {
-    Uninitialized,
-    AwaitingPlayerDecision,
-    GameOver(Winner),
-    PlayerStopedTakingCards
+/// A clickable stand-in for one of the action keys.
+struct Button {
+    rect: Rect,
+    label: &'static str,
+    action: Action
 }
 
 struct Game<'a> {
-    status: GameStatus,
-    deck: Vec<Card>,
-    used_cards: Vec<usize>,
-    player_hand: Vec<usize>,
-    casino_hand: Vec<usize>,
+    table: Table,
     canvas: Canvas<Window>,
-    texture_manager: TextureManager<'a>
+    texture_manager: TextureManager<'a>,
+    config: Config,
+    bindings: KeyBindings,
+    quips: Quips,
+    quip: Option<String>,
+    stats: Stats,
+    achievement_progress: AchievementProgress,
+    /// Short notices, such as freshly unlocked achievements, and how much
+    /// longer to show each.
+    toasts: Vec<(String, f32)>,
+    /// The bet the last round was dealt with, for a quick rebet.
+    last_bet: u32,
+    /// Hands left to play while in a tournament.
+    hands_remaining: Option<u32>,
+    leaderboard: Leaderboard,
+    /// The initials typed so far for a new high score.
+    initials: Option<String>,
+    /// Basic strategy plays the hands, at the minimum bet, until the
+    /// player takes over.
+    demo: bool,
+    /// Time since the demo's last move.
+    demo_elapsed: f32,
+    /// Testing keys from `--debug`: F5 redeals the round in play with
+    /// the bets returned, F6 reshuffles first.
+    debug_keys: bool,
+    /// Set once the felt image has failed to load, so it isn't retried
+    /// every frame.
+    felt_missing: bool,
+    /// Every choice made this session, when asked to keep a replay.
+    recording: Option<Replay>,
+    /// A recorded session being played back instead of taking input, with
+    /// how many of its events have been played.
+    playback: Option<(Replay, usize)>,
+    sounds: Option<Sounds>,
+    muted: bool,
+    quit: bool,
+    mouse_position: (i32, i32),
+    menu_selection: usize,
+    settings_selection: usize,
+    pause_selection: usize,
+    show_hints: bool,
+    show_count: bool,
+    show_history: bool,
+    /// The F3 overlay with the frame rate and texture cache size.
+    show_diagnostics: bool,
+    /// Frame time in seconds, averaged over the last few frames.
+    smoothed_dt: f32,
+    layout_width: u32,
+    layout_height: u32,
+    card_animations: HashMap<usize, CardAnimation>,
+    /// The image drawn for each default card path under the card theme.
+    card_art: HashMap<String, String>,
+    /// How far the dealer's hole card is through turning over, from 0 to 1.
+    hole_card_flip: Option<f32>,
+    /// The hand that just busted, `None` for the dealer's, and how much
+    /// longer it flashes for.
+    bust_flash: Option<(Option<usize>, f32)>,
+    /// Time since the dealer's last draw.
+    dealer_draw_elapsed: f32,
+    /// How long the shoe has been shuffling for.
+    shuffle_elapsed: f32
 }
 
 impl <'a> Game<'a> {
-    fn new(deck: Vec<Card>, canvas: Canvas<Window>, texture_manager: TextureManager<'a>) -> Game<'a> {
-        let game = Game {
-            status: GameStatus::Uninitialized,
-            deck: deck,
-            used_cards: Vec::<usize>::new(),
-            player_hand: Vec::<usize>::new(),
-            casino_hand: Vec::<usize>::new(),
+    fn new(table: Table, canvas: Canvas<Window>, texture_manager: TextureManager<'a>, config: Config,
+        quips: Quips, stats: Stats, sounds: Option<Sounds>) -> Result<Game<'a>, String> {
+        let bindings = KeyBindings::from_config(&config.keys)?;
+        let layout_width = config.window.width;
+        let layout_height = config.window.height;
+        let muted = config.ui.mute;
+        let card_art = themed_card_art(&config.theme.cards_dir);
+        let mut game = Game {
+            table: table,
             canvas: canvas,
-            texture_manager: texture_manager
+            texture_manager: texture_manager,
+            config: config,
+            bindings: bindings,
+            quips: quips,
+            quip: None,
+            stats: stats,
+            achievement_progress: AchievementProgress::default(),
+            toasts: Vec::<(String, f32)>::new(),
+            last_bet: 0,
+            hands_remaining: None,
+            leaderboard: Leaderboard::default(),
+            initials: None,
+            demo: false,
+            demo_elapsed: 0.0,
+            debug_keys: false,
+            felt_missing: false,
+            recording: None,
+            playback: None,
+            sounds: sounds,
+            muted: muted,
+            quit: false,
+            mouse_position: (0, 0),
+            menu_selection: 0,
+            settings_selection: 0,
+            pause_selection: 0,
+            show_hints: false,
+            show_count: false,
+            show_history: false,
+            show_diagnostics: false,
+            smoothed_dt: 0.0,
+            layout_width: layout_width,
+            layout_height: layout_height,
+            card_animations: HashMap::<usize, CardAnimation>::new(),
+            card_art: card_art,
+            hole_card_flip: None,
+            bust_flash: None,
+            dealer_draw_elapsed: 0.0,
+            shuffle_elapsed: 0.0
         };
+        game.fit_layout_to_window();
         
-        return game;
+        return Ok(game);
     }
 
-    fn exec_cycle(&mut self,  keycodes: &Vec<Keycode>) {
-        self.canvas.set_draw_color(Color::RGB(25, 120, 50));
-        self.canvas.clear();
+    /// Scales the board with the window. The layout keeps the configured
+    /// height and takes as much width as the window's aspect ratio gives it,
+    /// so cards and text grow and shrink together.
+    fn fit_layout_to_window(&mut self) {
+        let (width, height) = self.canvas.output_size().unwrap();
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        self.layout_height = self.config.window.height;
+        self.layout_width = ((self.layout_height as u64 * width as u64 / height as u64) as u32).max(MIN_LAYOUT_WIDTH);
+        self.canvas.set_logical_size(self.layout_width, self.layout_height).unwrap();
+    }
+
+    /// Runs one frame; `dt` is the time in seconds since the previous one.
+    fn exec_cycle(&mut self,  keycodes: &Vec<Keycode>, clicks: &Vec<(i32, i32)>, dt: f32) -> Result<(), String> {
+        let original_keycodes = keycodes;
+        self.render_table();
+
+        self.advance_card_animations(dt);
+
+        if keycodes.contains(&Keycode::Escape) {
+            self.handle_escape()?;
+        }
+        // Letters typed as initials aren't toggles.
+        let typing = self.initials.is_some();
+        if keycodes.contains(&Keycode::C) && !typing {
+            self.show_count = !self.show_count;
+        }
+        if keycodes.contains(&Keycode::V) && !typing {
+            self.muted = !self.muted;
+        }
+        if keycodes.contains(&Keycode::T) && !typing {
+            self.show_history = !self.show_history;
+        }
+        if keycodes.contains(&Keycode::F3) {
+            self.show_diagnostics = !self.show_diagnostics;
+        }
+        if keycodes.contains(&Keycode::A) && !typing {
+            self.demo = !self.demo;
+            self.demo_elapsed = 0.0;
+        }
 
-        match self.status {
+        // A replay plays the recorded choices instead of taking any input,
+        // and the demo presses the keys itself.
+        let no_keycodes = Vec::<Keycode>::new();
+        let no_clicks = Vec::<(i32, i32)>::new();
+        let demo_keycodes = if self.demo && self.playback.is_none() {
+            self.demo_keycodes(dt)
+        } else {
+            Vec::<Keycode>::new()
+        };
+        let (keycodes, clicks) = match self.playback {
+            Some(_) => {
+                let paused = matches!(self.table.status, GameStatus::Paused(_));
+                if !paused && (keycodes.contains(&Keycode::Space) || keycodes.contains(&Keycode::Right)) {
+                    self.play_next_event();
+                }
+                (&no_keycodes, &no_clicks)
+            },
+            None if self.demo => (&demo_keycodes, &no_clicks),
+            None => (keycodes, clicks),
+        };
+
+        let in_round = self.table.status.hole_card_hidden() && !matches!(self.table.status, GameStatus::Paused(_));
+        if self.debug_keys && in_round && (keycodes.contains(&Keycode::F5) || keycodes.contains(&Keycode::F6)) {
+            self.act(ReplayEvent::Redeal(keycodes.contains(&Keycode::F6)));
+            self.card_animations.clear();
+            self.toasts.push(("Round called off, bets returned".to_string(), TOAST_SECONDS));
+        }
+
+        let was_over = matches!(self.table.status, GameStatus::GameOver(_));
+        let previous_status = std::mem::discriminant(&self.table.status);
+        match self.table.status {
+            GameStatus::MainMenu => self.exec_main_menu(keycodes),
+            GameStatus::Settings => self.exec_settings(keycodes)?,
+            GameStatus::Achievements => self.exec_achievements(keycodes),
+            GameStatus::Stats => self.exec_stats(keycodes),
+            GameStatus::AwaitingBet => self.exec_game_awaiting_bet(keycodes),
+            GameStatus::Shuffling => self.exec_game_shuffling(dt)?,
             GameStatus::Uninitialized => self.exec_game_uninitialized(),
-            GameStatus::AwaitingPlayerDecision => self.exec_game_awaiting_player_decision(keycodes),
+            GameStatus::OfferingEarlySurrender => self.exec_game_offering_early_surrender(keycodes),
+            GameStatus::OfferingInsurance => self.exec_game_offering_insurance(keycodes),
+            GameStatus::OfferingEvenMoney => self.exec_game_offering_even_money(keycodes),
+            GameStatus::AwaitingPlayerDecision => self.exec_game_awaiting_player_decision(keycodes, clicks),
             GameStatus::GameOver(_) => self.exec_game_game_over(keycodes),
-            GameStatus::PlayerStopedTakingCards => self.exec_game_player_stopped_taking_cards()
+            GameStatus::PlayerStopedTakingCards => self.exec_game_player_stopped_taking_cards(dt),
+            GameStatus::Bankrupt => self.exec_game_bankrupt(keycodes),
+            GameStatus::TournamentOver => self.exec_tournament_over(keycodes)?,
+            // Drawn over the board below.
+            GameStatus::Paused(_) => {}
         }
 
-        self.render_hands();
+        if std::mem::discriminant(&self.table.status) != previous_status {
+            debug!("status now {:?}", self.table.status);
+        }
+
+        if let GameStatus::GameOver(results) = &self.table.status {
+            if !was_over {
+                if let Some(hands) = &mut self.hands_remaining {
+                    *hands = hands.saturating_sub(1);
+                }
+                self.quip = self.quips.pick(quip_situation(results)).cloned();
+                let winner = overall_winner(results);
+                // Only the player's own rounds count towards the record.
+                if self.playback.is_none() && !self.demo {
+                    self.stats.record(winner);
+                    self.stats.record_bets(results);
+                    let earned = self.achievement_progress.record_round(&self.table, self.config.betting.starting_bankroll);
+                    for achievement in earned {
+                        if self.stats.unlock(achievement) {
+                            self.toasts.push((format!("Achievement unlocked: {}", achievement.name()), TOAST_SECONDS));
+                        }
+                    }
+                }
+
+                // A bust has already been heard when the hand went over.
+                let all_bust = results.iter().all(|result| result.reason == RoundReason::PlayerBust);
+                match winner {
+                    Winner::Player => self.play_sound(Sound::Win),
+                    Winner::Casino if !all_bust => self.play_sound(Sound::Lose),
+                    _ => {}
+                }
+            }
+        }
+
+        self.render_discard_pile()?;
+        self.render_hands()?;
+        self.render_bust_flash(dt);
+        self.render_bankroll()?;
+        if self.show_history && !self.table.history.is_empty() {
+            self.render_history();
+        }
+        self.render_toasts(dt);
+        if let Some((replay, played)) = &self.playback {
+            let progress = format!("Replay {}/{}: {}", played, replay.events.len(), REPLAY_NEXT_TEXT);
+            self.render_text(&progress, 0, 390, 40);
+        } else if self.demo {
+            self.render_text(DEMO_TEXT, 0, 390, 40);
+        }
+        if let GameStatus::Paused(_) = self.table.status {
+            self.exec_pause(original_keycodes);
+        }
+        self.smoothed_dt = if self.smoothed_dt == 0.0 {
+            dt
+        } else {
+            self.smoothed_dt + (dt - self.smoothed_dt) * FRAME_TIME_SMOOTHING
+        };
+        if self.show_diagnostics {
+            self.render_diagnostics();
+        }
         self.canvas.present();
+
+        return Ok(());
     }
 
-    fn exec_game_uninitialized(&mut self) {
-        let mut random_card = self.get_random_card().unwrap();
-        self.casino_hand.push(random_card);
+    /// The key the demo presses this frame, if it's time for its next
+    /// move: the minimum bet, no insurance or even money, and basic
+    /// strategy for every hand. Running out of chips ends the demo.
+    fn demo_keycodes(&mut self, dt: f32) -> Vec<Keycode> {
+        self.demo_elapsed += dt;
+        if self.demo_elapsed < DEMO_MOVE_SECONDS {
+            return Vec::<Keycode>::new();
+        }
+        self.demo_elapsed = 0.0;
 
-        random_card = self.get_random_card().unwrap();
-        self.player_hand.push(random_card);
+        let keycode = match &self.table.status {
+            GameStatus::AwaitingBet => {
+                self.table.current_bet = self.config.betting.min_bet;
+                Keycode::Return
+            },
+            GameStatus::OfferingEarlySurrender | GameStatus::OfferingInsurance => self.bindings.stand(),
+            GameStatus::OfferingEvenMoney => Keycode::N,
+            GameStatus::AwaitingPlayerDecision => match playable_action(&self.table, suggest(&self.table)) {
+                Action::Hit => self.bindings.hit(),
+                Action::Stand => self.bindings.stand(),
+                Action::DoubleDown => self.bindings.double(),
+                Action::Split => self.bindings.split(),
+            },
+            GameStatus::GameOver(_) => self.bindings.restart(),
+            GameStatus::Bankrupt => {
+                self.demo = false;
+                return Vec::<Keycode>::new();
+            },
+            _ => return Vec::<Keycode>::new(),
+        };
 
-        random_card = self.get_random_card().unwrap();
-        self.player_hand.push(random_card);
+        return vec![keycode];
+    }
 
-        let player_score = self.calculate_hand_score(&self.player_hand);
+    /// Carries out a choice made at the table, keeping it for the replay
+    /// when recording.
+    fn act(&mut self, event: ReplayEvent) {
+        event.apply(&mut self.table);
+        if let Some(recording) = &mut self.recording {
+            recording.events.push(event);
+        }
+    }
 
-        if player_score == TWENTY_ONE {
-            self.status = GameStatus::PlayerStopedTakingCards;
+    fn play_next_event(&mut self) {
+        let event = match &mut self.playback {
+            Some((replay, played)) if *played < replay.events.len() => {
+                *played += 1;
+                replay.events[*played - 1]
+            },
+            _ => return,
+        };
+
+        let acting_hand = self.table.active_hand;
+        if let ReplayEvent::NextRound | ReplayEvent::Restart(_) = event {
+            self.quip = None;
+        }
+        event.apply(&mut self.table);
+        if let ReplayEvent::Hit | ReplayEvent::DoubleDown = event {
+            self.mark_bust(acting_hand);
+        }
+    }
+
+    /// Backs out of a submenu to the main menu; at the table it does what
+    /// `escape_action` says.
+    fn handle_escape(&mut self) -> Result<(), String> {
+        if matches!(self.table.status, GameStatus::Settings) {
+            return self.leave_settings();
+        }
+
+        // Restarting or saving from the pause menu only makes sense mid-game.
+        let at_table = self.at_table();
+        let status = std::mem::replace(&mut self.table.status, GameStatus::Uninitialized);
+        self.table.status = match status {
+            GameStatus::Paused(previous) => *previous,
+            GameStatus::Achievements | GameStatus::Stats => GameStatus::MainMenu,
+            _ if !at_table => status,
+            _ if self.config.ui.escape_action == EscapeAction::Pause => {
+                self.pause_selection = PAUSE_RESUME;
+                GameStatus::Paused(Box::new(status))
+            },
+            _ => {
+                self.quit = true;
+                status
+            }
+        };
+
+        return Ok(());
+    }
+
+    fn exec_main_menu(&mut self, keycodes: &Vec<Keycode>) {
+        let x = self.layout_width as i32 / 2 - 150;
+        self.render_text(WIN_NAME, x - 50, 150, 150);
+        for (idx, item) in MENU_ITEMS.iter().enumerate() {
+            let text = if idx == self.menu_selection {
+                format!("> {}", item)
+            } else {
+                item.to_string()
+            };
+
+            self.render_text(&text, x, 400 + idx as i32 * 80, 60);
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(MENU_HELP_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        self.menu_selection = menu_step(self.menu_selection, MENU_ITEMS.len(), keycodes);
+        if keycodes.contains(&Keycode::Return) {
+            match self.menu_selection {
+                MENU_START => self.table.status = GameStatus::AwaitingBet,
+                MENU_TOURNAMENT => self.start_tournament(),
+                // A rules change isn't part of a replay, so it would break one.
+                MENU_SETTINGS if self.recording.is_some() || self.playback.is_some() => {
+                    self.toasts.push(("Can't change the rules during a replay".to_string(), TOAST_SECONDS));
+                }
+                MENU_SETTINGS => self.table.status = GameStatus::Settings,
+                MENU_ACHIEVEMENTS => self.table.status = GameStatus::Achievements,
+                MENU_STATS => self.table.status = GameStatus::Stats,
+                _ => self.quit = true,
+            }
+        }
+    }
+
+    /// The rules in play, changed with the arrow keys. Leaving the screen
+    /// hands them to the table for the next round and saves them to the
+    /// config file.
+    fn exec_settings(&mut self, keycodes: &Vec<Keycode>) -> Result<(), String> {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        let rules = &self.config.rules;
+        let surrender = match (rules.surrender_allowed, rules.early_surrender) {
+            (_, true) => "early",
+            (true, false) => "late",
+            (false, false) => "no",
+        };
+        let lines = [
+            format!("Decks: {}", rules.decks),
+            format!("Dealer hits soft 17: {}", yes_no(rules.dealer_hits_soft_17)),
+            format!("Dealer wins ties: {}", yes_no(rules.dealer_wins_ties)),
+            format!("Five-card Charlie: {}", yes_no(rules.five_card_charlie)),
+            format!("Blackjack pays: {}:{}", rules.blackjack_payout.0, rules.blackjack_payout.1),
+            format!("Surrender: {}", surrender),
+            format!("Split up to hands: {}", rules.max_split_hands),
+            format!("Resplit aces: {}", yes_no(rules.resplit_aces)),
+            format!("Split aces get one card: {}", yes_no(rules.split_aces_one_card)),
+            format!("Dealer peeks for blackjack: {}", yes_no(rules.dealer_peeks)),
+            format!("Dealing: {}", if rules.dealing == Dealing::American { "American (hole card)" } else { "European (no hole card)" }),
+            format!("Colorblind suit letters: {}", yes_no(self.config.ui.colorblind_mode)),
+            format!("Flash busted hands: {}", yes_no(self.config.ui.bust_flash)),
+        ];
+        for (idx, line) in lines.iter().enumerate() {
+            let text = if idx == self.settings_selection {
+                format!("> {}", line)
+            } else {
+                line.to_string()
+            };
+
+            self.render_text(&text, 100, 60 + idx as i32 * 55, 45);
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(SETTINGS_HELP_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        self.settings_selection = menu_step(self.settings_selection, SETTINGS_COUNT, keycodes);
+        let step: i32 = if keycodes.contains(&Keycode::Right) {
+            1
+        } else if keycodes.contains(&Keycode::Left) {
+            -1
         } else {
-            self.status = GameStatus::AwaitingPlayerDecision;
+            0
+        };
+        if step != 0 {
+            let rules = &mut self.config.rules;
+            match self.settings_selection {
+                0 => rules.decks = (rules.decks as i32 + step).clamp(1, MAX_DECKS as i32) as usize,
+                1 => rules.dealer_hits_soft_17 = !rules.dealer_hits_soft_17,
+                2 => rules.dealer_wins_ties = !rules.dealer_wins_ties,
+                3 => rules.five_card_charlie = !rules.five_card_charlie,
+                4 => {
+                    let current = BLACKJACK_PAYOUTS.iter().position(|payout| *payout == rules.blackjack_payout);
+                    let next = match current {
+                        Some(idx) => (idx as i32 + step).rem_euclid(BLACKJACK_PAYOUTS.len() as i32) as usize,
+                        None => 0,
+                    };
+                    rules.blackjack_payout = BLACKJACK_PAYOUTS[next];
+                },
+                // No surrender, late, early, round again; never both at once.
+                5 => {
+                    let current = if rules.early_surrender { 2 } else if rules.surrender_allowed { 1 } else { 0 };
+                    let next = (current + step).rem_euclid(3);
+                    rules.surrender_allowed = next == 1;
+                    rules.early_surrender = next == 2;
+                },
+                6 => rules.max_split_hands = (rules.max_split_hands as i32 + step).clamp(1, MAX_SPLIT_HANDS as i32) as usize,
+                7 => rules.resplit_aces = !rules.resplit_aces,
+                8 => rules.split_aces_one_card = !rules.split_aces_one_card,
+                9 => rules.dealer_peeks = !rules.dealer_peeks,
+                10 => rules.dealing = match rules.dealing {
+                    Dealing::American => Dealing::European,
+                    Dealing::European => Dealing::American,
+                },
+                11 => self.config.ui.colorblind_mode = !self.config.ui.colorblind_mode,
+                _ => self.config.ui.bust_flash = !self.config.ui.bust_flash,
+            }
+        }
+
+        if keycodes.contains(&Keycode::Return) {
+            self.leave_settings()?;
+        }
+
+        return Ok(());
+    }
+
+    /// Back to the main menu, playing on under the rules as they were left.
+    fn leave_settings(&mut self) -> Result<(), String> {
+        self.table.set_rules(self.config.rules.clone());
+        self.table.status = GameStatus::MainMenu;
+        return save_settings(CONFIG_PATH, &self.config);
+    }
+
+    fn exec_achievements(&mut self, keycodes: &Vec<Keycode>) {
+        let lines: Vec<String> = Achievement::iterator()
+            .map(|achievement| {
+                let mark = if self.stats.achievements.contains(&achievement) { "[x]" } else { "[ ]" };
+                return format!("{} {}", mark, achievement.name());
+            })
+            .collect();
+        for (idx, line) in lines.iter().enumerate() {
+            self.render_text(line, 100, 100 + idx as i32 * 70, 50);
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(ENTER_TO_GO_BACK_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&Keycode::Return) {
+            self.table.status = GameStatus::MainMenu;
+        }
+    }
+
+    fn exec_stats(&mut self, keycodes: &Vec<Keycode>) {
+        let stats = &self.stats;
+        let lines = [
+            format!("Rounds played: {}", stats.rounds()),
+            format!("Won {} / lost {} / tied {}", stats.player_wins, stats.casino_wins, stats.ties),
+            format!("Win rate: {:.1}%", stats.win_percentage()),
+            format!("Average bet: {:.1}", stats.average_bet()),
+            format!("Net winnings: {:+}", stats.net),
+            format!("House edge: {:.2}%", stats.house_edge()),
+        ];
+        for (idx, line) in lines.iter().enumerate() {
+            self.render_text(line, 100, 100 + idx as i32 * 70, 50);
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(ENTER_TO_GO_BACK_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&Keycode::Return) {
+            self.table.status = GameStatus::MainMenu;
+        }
+    }
+
+    fn exec_game_awaiting_bet(&mut self, keycodes: &Vec<Keycode>) {
+        if self.table.is_bankrupt(self.config.betting.min_bet) {
+            if self.hands_remaining.is_some() {
+                self.finish_tournament();
+            } else {
+                self.table.status = GameStatus::Bankrupt;
+            }
+            return;
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(CHOOSE_BET_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 160, self.layout_width, 80)).unwrap();
+        self.canvas.copy(
+            &self.texture_manager.load_text(ENTER_TO_DEAL_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        let (min_bet, max_bet) = (self.config.betting.min_bet, self.config.betting.max_bet);
+        self.render_text(&format!("Table limits: {} - {}", min_bet, max_bet), 0, 290, 50);
+
+        let step = self.config.betting.bet_step;
+        for keycode in keycodes {
+            let bet = match keycode {
+                Keycode::Equals | Keycode::Plus | Keycode::KpPlus => self.table.current_bet + step,
+                Keycode::Minus | Keycode::KpMinus => self.table.current_bet.saturating_sub(step),
+                _ => match number_key_value(*keycode) {
+                    Some(multiplier) => multiplier * step,
+                    None => continue,
+                },
+            };
+
+            self.table.current_bet = clamp_bet(bet, min_bet, max_bet, self.table.largest_bankroll());
+        }
+
+        if keycodes.contains(&Keycode::R) {
+            self.repeat_last_bet();
+        } else if keycodes.contains(&Keycode::Return) && self.table.can_place_bet(min_bet, max_bet) {
+            self.last_bet = self.table.current_bet;
+            self.act(ReplayEvent::Bet(self.table.current_bet));
+        }
+    }
+
+    /// Deals straight away with the last round's bet, lowered to what the
+    /// richest player can still cover.
+    fn repeat_last_bet(&mut self) {
+        if self.last_bet == 0 {
+            return;
+        }
+
+        let (min_bet, max_bet) = (self.config.betting.min_bet, self.config.betting.max_bet);
+        let bet = clamp_bet(self.last_bet, min_bet, max_bet, self.table.largest_bankroll());
+        if bet < self.last_bet {
+            self.toasts.push((format!("Bet lowered to {} to fit the table and bankroll", bet), TOAST_SECONDS));
+        }
+        self.table.current_bet = bet;
+        if self.table.can_place_bet(min_bet, max_bet) {
+            self.act(ReplayEvent::Bet(bet));
+        }
+    }
+
+    /// Shuffles the shoe straight away, so the count and the cards left
+    /// reset on screen, then riffles a few card backs for a moment before
+    /// the deal.
+    fn exec_game_shuffling(&mut self, dt: f32) -> Result<(), String> {
+        if self.shuffle_elapsed == 0.0 {
+            self.table.reshuffle();
+        }
+        self.shuffle_elapsed += dt;
+
+        let center_x = self.layout_width as i32 / 2 - CARD_WIDTH as i32 / 2;
+        let back = self.texture_manager.load_texture(&self.card_art_path(CARD_BACK_PATH))?;
+        for idx in 0..6 {
+            let phase = self.shuffle_elapsed * 12.0 + idx as f32;
+            let side = if idx % 2 == 0 { -1.0 } else { 1.0 };
+            let x = center_x + (side * phase.sin().abs() * 60.0) as i32;
+            self.canvas.copy(&back, None, Rect::new(x, 250 - idx * 4, CARD_WIDTH, CARD_HEIGHT)).unwrap();
+        }
+        self.canvas.copy(
+            &self.texture_manager.load_text(SHUFFLING_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 160, self.layout_width, 80)).unwrap();
+
+        if self.shuffle_elapsed >= SHUFFLE_SECONDS {
+            self.shuffle_elapsed = 0.0;
+            self.table.status = GameStatus::Uninitialized;
+        }
+
+        return Ok(());
+    }
+
+    fn exec_game_uninitialized(&mut self) {
+        self.table.deal();
+    }
+
+    fn exec_game_offering_early_surrender(&mut self, keycodes: &Vec<Keycode>) {
+        self.canvas.copy(
+            &self.texture_manager.load_text(EARLY_SURRENDER_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 160, self.layout_width, 80)).unwrap();
+        self.canvas.copy(
+            &self.texture_manager.load_text(&key_prompt(self.bindings.stand(), DECLINE_EARLY_SURRENDER_TEXT)), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&Keycode::S) {
+            self.act(ReplayEvent::EarlySurrender(true));
+        } else if keycodes.contains(&self.bindings.stand()) {
+            self.act(ReplayEvent::EarlySurrender(false));
         }
     }
 
-    fn exec_game_awaiting_player_decision(&mut self, keycodes: &Vec<Keycode>) {
+    fn exec_game_offering_insurance(&mut self, keycodes: &Vec<Keycode>) {
+        let insurance_bet = insurance_stake(self.table.current_bet);
         self.canvas.copy(
-            &self.texture_manager.load_texture(TAKE_ANOTHER_CARD_TEXT), None, 
-            Rect::new(0, HEIGHT as i32 - 160,WIDTH, 80)).unwrap();
+            &self.texture_manager.load_dynamic_text(&format!("Insurance for {}? Press I to take it", insurance_bet)), None, 
+            Rect::new(0, self.layout_height as i32 - 160, self.layout_width, 80)).unwrap();
         self.canvas.copy(
-            &self.texture_manager.load_texture(STOP_TAKING_CARDS_TEXT), None, 
-            Rect::new(0, HEIGHT as i32 - 80,WIDTH, 80)).unwrap();
-
-        if keycodes.contains(&Keycode::F) {
-            let random_card = self.get_random_card().unwrap();
-            self.player_hand.push(random_card);
-
-            let player_score = self.calculate_hand_score(&self.player_hand);
-            if player_score > TWENTY_ONE {
-                self.status = GameStatus::GameOver(Winner::Casino);   
-            } else if player_score == TWENTY_ONE {
-                self.status = GameStatus::PlayerStopedTakingCards; 
+            &self.texture_manager.load_text(&key_prompt(self.bindings.stand(), DECLINE_INSURANCE_TEXT)), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&Keycode::I) {
+            self.act(ReplayEvent::Insurance(true));
+        } else if keycodes.contains(&self.bindings.stand()) {
+            self.act(ReplayEvent::Insurance(false));
+        }
+    }
+
+    fn exec_game_offering_even_money(&mut self, keycodes: &Vec<Keycode>) {
+        self.canvas.copy(
+            &self.texture_manager.load_text(TAKE_EVEN_MONEY_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 160, self.layout_width, 80)).unwrap();
+        self.canvas.copy(
+            &self.texture_manager.load_text(DECLINE_EVEN_MONEY_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&Keycode::Y) {
+            self.act(ReplayEvent::EvenMoney(true));
+        } else if keycodes.contains(&Keycode::N) {
+            self.act(ReplayEvent::EvenMoney(false));
+        }
+    }
+
+    fn exec_game_awaiting_player_decision(&mut self, keycodes: &Vec<Keycode>, clicks: &Vec<(i32, i32)>) {
+        let can_hit = self.table.can_hit();
+        if can_hit {
+            self.canvas.copy(
+                &self.texture_manager.load_text(&key_prompt(self.bindings.hit(), TAKE_ANOTHER_CARD_TEXT)), None, 
+                Rect::new(0, self.layout_height as i32 - 160, self.layout_width, 80)).unwrap();
+        }
+        self.canvas.copy(
+            &self.texture_manager.load_text(&key_prompt(self.bindings.stand(), STOP_TAKING_CARDS_TEXT)), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if self.table.bankrolls.len() > 1 {
+            self.render_text(&format!("Player {} to play", self.table.active_seat() + 1), 0, 440, 50);
+        }
+
+        let can_double = self.table.can_double();
+        let can_split = self.table.can_split();
+        let can_surrender = self.table.can_surrender();
+
+        let mut options = Vec::<String>::new();
+        if can_surrender {
+            options.push(SURRENDER_TEXT.to_string());
+        }
+        if can_double {
+            options.push(key_prompt(self.bindings.double(), DOUBLE_DOWN_TEXT));
+        }
+        if can_split {
+            options.push(key_prompt(self.bindings.split(), SPLIT_TEXT));
+        }
+        if !options.is_empty() {
+            self.canvas.copy(
+                &self.texture_manager.load_dynamic_text(&options.join("    ")), None, 
+                Rect::new(0, self.layout_height as i32 - 240, self.layout_width, 80)).unwrap();
+        }
+
+        let can_bust = self.table.player_hands[self.table.active_hand].cards.can_bust();
+        if !can_bust && self.config.ui.safe_hit_cue {
+            self.canvas.copy(
+                &self.texture_manager.load_text(SAFE_TO_HIT_TEXT), None, 
+                Rect::new(0, self.layout_height as i32 - 320, self.layout_width, 80)).unwrap();
+        }
+
+        let mut actions = vec![(Action::Stand, STAND_BUTTON_TEXT)];
+        if can_hit {
+            actions.insert(0, (Action::Hit, HIT_BUTTON_TEXT));
+        }
+        if can_double {
+            actions.push((Action::DoubleDown, DOUBLE_BUTTON_TEXT));
+        }
+        if can_split {
+            actions.push((Action::Split, SPLIT_BUTTON_TEXT));
+        }
+        let buttons = self.layout_buttons(&actions);
+        for button in &buttons {
+            self.render_button(button);
+        }
+        let clicked = buttons.iter()
+            .find(|button| clicks.iter().any(|click| button.rect.contains_point(*click)))
+            .map(|button| button.action);
+
+        if keycodes.contains(&Keycode::H) {
+            self.show_hints = !self.show_hints;
+        }
+        if self.show_hints {
+            let hint = match suggest(&self.table) {
+                Action::Hit => HIT_BUTTON_TEXT,
+                Action::Stand => STAND_BUTTON_TEXT,
+                Action::DoubleDown => DOUBLE_BUTTON_TEXT,
+                Action::Split => SPLIT_BUTTON_TEXT,
+            };
+            self.render_text(&format!("Hint: {}", hint), 0, 230, 50);
+            if can_hit {
+                let chance = self.table.bust_probability() * 100.0;
+                self.render_text(&format!("Bust on a hit: {:.0}%", chance), 0, 280, 40);
+            }
+        }
+
+        // Auto-hit leaves the opening hand alone while a double or split is on
+        // offer, and gives way to anything the player asks for themselves.
+        let auto_hit = can_hit && !can_bust && options.is_empty() && self.config.ui.auto_hit_safe_hands;
+
+        let requested = |key: Keycode, action: Action| keycodes.contains(&key) || clicked == Some(action);
+        let decision = pick_decision(&[
+            (requested(self.bindings.stand(), Action::Stand), ReplayEvent::Stand),
+            (requested(self.bindings.hit(), Action::Hit) && can_hit, ReplayEvent::Hit),
+            (requested(self.bindings.double(), Action::DoubleDown) && can_double, ReplayEvent::DoubleDown),
+            (requested(self.bindings.split(), Action::Split) && can_split, ReplayEvent::Split),
+            (keycodes.contains(&Keycode::S) && can_surrender, ReplayEvent::Surrender),
+            (auto_hit, ReplayEvent::Hit),
+        ]);
+
+        let acting_hand = self.table.active_hand;
+        if let Some(event) = decision {
+            self.act(event);
+            if matches!(event, ReplayEvent::Hit | ReplayEvent::DoubleDown) {
+                self.mark_bust(acting_hand);
             }
-        } else if keycodes.contains(&Keycode::E) {
-            self.status = GameStatus::PlayerStopedTakingCards;
         }
     }
 
-    fn exec_game_game_over(&mut self, keycodes: &Vec<Keycode>) {
-        let mut winner = &Winner::Tie;
-        match &self.status {
-            GameStatus::GameOver(win) => {
-                winner = win;
+    fn mark_bust(&mut self, hand: usize) {
+        if self.table.player_hands[hand].cards.is_bust() {
+            self.play_sound(Sound::Bust);
+            self.start_bust_flash(Some(hand));
+        }
+    }
+
+    fn start_bust_flash(&mut self, hand: Option<usize>) {
+        if self.config.ui.bust_flash {
+            self.bust_flash = Some((hand, BUST_FLASH_SECONDS));
+        }
+    }
+
+    /// Fades a red wash out over the hand that just busted.
+    fn render_bust_flash(&mut self, dt: f32) {
+        let (hand, remaining) = match self.bust_flash {
+            Some((hand, remaining)) if remaining > 0.0 => (hand, remaining - dt),
+            _ => {
+                self.bust_flash = None;
+                return;
+            }
+        };
+        self.bust_flash = Some((hand, remaining));
+
+        let (x, y, cards, available_width) = match hand {
+            Some(idx) if idx < self.table.player_hands.len() => {
+                let hand_width = self.layout_width as i32 / self.table.player_hands.len() as i32;
+                (self.player_hand_x(idx), 500, self.table.player_hands[idx].cards.len(), hand_width)
             },
+            None if !self.table.casino_hand.is_empty() => (0, 60, self.table.casino_hand.len(), self.layout_width as i32 - 350),
             _ => return,
+        };
+        let width = (cards as i32 - 1) * card_step(cards, available_width) + CARD_WIDTH as i32;
+        let alpha = (BUST_FLASH_COLOR.a as f32 * (remaining / BUST_FLASH_SECONDS).max(0.0)) as u8;
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(BUST_FLASH_COLOR.r, BUST_FLASH_COLOR.g, BUST_FLASH_COLOR.b, alpha));
+        self.canvas.fill_rect(Rect::new(x, y, width as u32, CARD_HEIGHT)).unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    fn play_sound(&self, sound: Sound) {
+        if let Some(sounds) = &self.sounds {
+            if !self.muted {
+                sounds.play(sound);
+            }
         }
+    }
 
-        match winner {
-            Winner::Casino => self.canvas.copy(
-                &self.texture_manager.load_texture(CASINO_WINS_TEXT), None, 
-                Rect::new(0, HEIGHT as i32 - 160,WIDTH, 80)).unwrap(),
-            Winner::Player => self.canvas.copy(
-                &self.texture_manager.load_texture(PLAYER_WINS_TEXT), None, 
-                Rect::new(0, HEIGHT as i32 - 160,WIDTH, 80)).unwrap(),
-            Winner::Tie => self.canvas.copy(
-                &self.texture_manager.load_texture(ITS_A_TIE_TEXT), None, 
-                Rect::new(0, HEIGHT as i32 - 160,WIDTH, 80)).unwrap(),
+    /// Stacks the buttons down the right edge of the table, clear of the
+    /// bankroll and the player's cards.
+    fn layout_buttons(&self, actions: &[(Action, &'static str)]) -> Vec<Button> {
+        let x = self.layout_width as i32 - 220;
+        return actions.iter().enumerate().map(|(idx, (action, label))| Button {
+            rect: Rect::new(x, 250 + idx as i32 * 60, 200, 50),
+            label: label,
+            action: *action
+        }).collect();
+    }
+
+    fn render_button(&mut self, button: &Button) {
+        let color = if button.rect.contains_point(self.mouse_position) {
+            Color::RGB(90, 90, 90)
+        } else {
+            Color::RGB(40, 40, 40)
+        };
+
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(button.rect).unwrap();
+        self.render_text(button.label, button.rect.x() + 10, button.rect.y() + 5, 40);
+    }
+
+    fn exec_game_game_over(&mut self, keycodes: &Vec<Keycode>) {
+        let results = match &self.table.status {
+            GameStatus::GameOver(results) => results,
+            _ => return,
+        };
+
+        let text = if results.len() == 1 {
+            let result = &results[0];
+            match result.winner {
+                Winner::Player if result.reason == RoundReason::PlayerBlackjack => PLAYER_BLACKJACK_TEXT.to_string(),
+                Winner::Player if result.reason == RoundReason::FiveCardCharlie => FIVE_CARD_CHARLIE_TEXT.to_string(),
+                Winner::Player if result.reason == RoundReason::EvenMoney => EVEN_MONEY_TEXT.to_string(),
+                Winner::Casino if result.reason == RoundReason::Surrender => YOU_SURRENDERED_TEXT.to_string(),
+                Winner::Casino => CASINO_WINS_TEXT.to_string(),
+                Winner::Player => PLAYER_WINS_TEXT.to_string(),
+                Winner::Tie if self.config.ui.explain_ties => tie_explanation_text(result.player_total),
+                Winner::Tie => ITS_A_TIE_TEXT.to_string(),
+            }
+        } else {
+            match overall_winner(results) {
+                Winner::Player => PLAYER_WINS_TEXT.to_string(),
+                Winner::Casino => CASINO_WINS_TEXT.to_string(),
+                Winner::Tie => ITS_A_TIE_TEXT.to_string(),
+            }
+        };
+
+        let staked: u32 = results.iter().map(|result| result.bet).sum();
+        let paid: u32 = results.iter().map(|result| result.payout).sum();
+
+        self.render_outcome(&text);
+        self.render_quip();
+
+        if paid > staked {
+            self.render_text(&format!("You won {}", paid - staked), 0, 290, 50);
+        } else if paid < staked {
+            self.render_text(&format!("You lost {}", staked - paid), 0, 290, 50);
+        }
+
+        if self.hands_remaining == Some(0) {
+            self.canvas.copy(
+                &self.texture_manager.load_text(&key_prompt(self.bindings.restart(), SEE_RESULTS_TEXT)), None, 
+                Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+            if keycodes.contains(&self.bindings.restart()) {
+                self.quip = None;
+                self.finish_tournament();
+            }
+            return;
         }
 
         self.canvas.copy(
-            &self.texture_manager.load_texture(N_TO_RESTART_THE_GAME), None, 
-            Rect::new(0, HEIGHT as i32 - 80,WIDTH, 80)).unwrap();
+            &self.texture_manager.load_text(&key_prompt(self.bindings.restart(), RESTART_THE_GAME_TEXT)), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
 
-        if keycodes.contains(&Keycode::N) {
-            self.status = GameStatus::Uninitialized;
-            self.used_cards = Vec::<usize>::new();
-            self.player_hand = Vec::<usize>::new();
-            self.casino_hand = Vec::<usize>::new();
+        if keycodes.contains(&self.bindings.restart()) {
+            self.act(ReplayEvent::NextRound);
+            self.quip = None;
+        } else if keycodes.contains(&Keycode::R) {
+            self.act(ReplayEvent::NextRound);
+            self.quip = None;
+            self.repeat_last_bet();
+        } else if keycodes.contains(&Keycode::M) {
+            self.act(ReplayEvent::NextRound);
+            self.table.status = GameStatus::MainMenu;
+            self.hands_remaining = None;
+            self.quip = None;
         }
     }
 
-    fn exec_game_player_stopped_taking_cards(&mut self) {
-        let player_score = self.calculate_hand_score(&self.player_hand);
-        let mut casino_score = self.calculate_hand_score(&self.casino_hand);
+    /// Starts a fresh bankroll on a fixed number of hands.
+    fn start_tournament(&mut self) {
+        self.act(ReplayEvent::Restart(self.config.betting.starting_bankroll));
+        self.table.current_bet = self.config.betting.min_bet;
+        self.hands_remaining = Some(TOURNAMENT_HANDS);
+    }
+
+    /// Ends the tournament on the current bankroll, asking for initials if
+    /// it makes the leaderboard.
+    fn finish_tournament(&mut self) {
+        self.act(ReplayEvent::NextRound);
+        self.table.status = GameStatus::TournamentOver;
+        self.hands_remaining = None;
+        self.initials = if self.leaderboard.qualifies(self.table.largest_bankroll()) {
+            Some(String::new())
+        } else {
+            None
+        };
+    }
+
+    /// The final bankroll and the leaderboard, after taking the player's
+    /// initials for a new high score.
+    fn exec_tournament_over(&mut self, keycodes: &Vec<Keycode>) -> Result<(), String> {
+        let score = self.table.largest_bankroll();
+        self.render_outcome(TOURNAMENT_OVER_TEXT);
+        self.render_text(&format!("Final bankroll: {}", score), 0, 290, 50);
+
+        if let Some(initials) = &mut self.initials {
+            for keycode in keycodes {
+                match letter_key_value(*keycode) {
+                    Some(letter) if initials.len() < INITIALS_LENGTH => initials.push(letter),
+                    _ if *keycode == Keycode::Backspace => {
+                        initials.pop();
+                    },
+                    _ => {}
+                }
+            }
 
-        while casino_score < CASINO_STOP_SCORE && casino_score <= player_score {
-            let random_card = self.get_random_card().unwrap();
-            self.casino_hand.push(random_card);
+            let text = format!("Initials: {}_", initials);
+            let entered = keycodes.contains(&Keycode::Return) && !initials.is_empty();
+            self.render_text(&text, 0, 350, 50);
+            self.canvas.copy(
+                &self.texture_manager.load_text(ENTER_INITIALS_TEXT), None, 
+                Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
 
-            casino_score = self.calculate_hand_score(&self.casino_hand);
+            if entered {
+                let initials = self.initials.take().unwrap();
+                self.leaderboard.insert(&initials, score);
+                self.leaderboard.save(LEADERBOARD_PATH)?;
+            }
+            return Ok(());
+        }
+
+        let entries: Vec<String> = self.leaderboard.entries.iter().enumerate()
+            .map(|(idx, entry)| format!("{}. {} {}", idx + 1, entry.name, entry.bankroll))
+            .collect();
+        for (idx, entry) in entries.iter().enumerate() {
+            self.render_text(entry, 0, 350 + idx as i32 * 40, 40);
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(ENTER_TO_GO_BACK_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&Keycode::Return) {
+            self.act(ReplayEvent::Restart(self.config.betting.starting_bankroll));
+            self.table.current_bet = self.config.betting.min_bet;
+            self.table.status = GameStatus::MainMenu;
+        }
+
+        return Ok(());
+    }
+
+    /// Dims the board and offers to resume the paused round, start over
+    /// with fresh bankrolls, or quit.
+    fn exec_pause(&mut self, keycodes: &Vec<Keycode>) {
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 170));
+        self.canvas.fill_rect(None).unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+
+        let x = self.layout_width as i32 / 2 - 150;
+        self.render_text(PAUSED_TEXT, x, 200, 120);
+        for (idx, item) in PAUSE_ITEMS.iter().enumerate() {
+            let text = if idx == self.pause_selection {
+                format!("> {}", item)
+            } else {
+                item.to_string()
+            };
+
+            self.render_text(&text, x, 400 + idx as i32 * 80, 60);
+        }
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(PAUSE_HELP_TEXT), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        self.pause_selection = menu_step(self.pause_selection, PAUSE_ITEMS.len(), keycodes);
+        if !keycodes.contains(&Keycode::Return) {
+            return;
+        }
+
+        let status = std::mem::replace(&mut self.table.status, GameStatus::Uninitialized);
+        match self.pause_selection {
+            PAUSE_RESUME => {
+                if let GameStatus::Paused(previous) = status {
+                    self.table.status = *previous;
+                }
+            },
+            PAUSE_RESTART if self.playback.is_some() => {
+                self.table.status = status;
+                self.toasts.push(("Can't restart during a replay".to_string(), TOAST_SECONDS));
+            },
+            PAUSE_RESTART => {
+                self.act(ReplayEvent::Restart(self.config.betting.starting_bankroll));
+                self.table.current_bet = self.config.betting.min_bet;
+                self.hole_card_flip = None;
+                self.dealer_draw_elapsed = 0.0;
+                self.hands_remaining = None;
+                self.quip = None;
+            },
+            PAUSE_SAVE => {
+                if let GameStatus::Paused(previous) = status {
+                    self.table.status = *previous;
+                }
+                self.save_game(SAVE_PATH);
+            },
+            PAUSE_LOAD => {
+                // Stays paused if there's nothing to load.
+                self.table.status = status;
+                self.load_game(SAVE_PATH);
+            },
+            _ => {
+                self.table.status = status;
+                self.quit = true;
+            },
+        }
+    }
+
+    /// Saves the table as it stands, to carry on from later with `load_game`.
+    fn save_game(&mut self, path: &str) {
+        let notice = match self.table.save(path) {
+            Ok(()) => "Game saved".to_string(),
+            Err(err) => err,
+        };
+        self.toasts.push((notice, TOAST_SECONDS));
+    }
+
+    /// Carries on from the table saved at `path`, under the rules it was
+    /// saved with. A tournament in progress ends, and a replay being
+    /// recorded or played back can't jump to another table.
+    fn load_game(&mut self, path: &str) {
+        if self.recording.is_some() || self.playback.is_some() {
+            self.toasts.push(("Can't load a game during a replay".to_string(), TOAST_SECONDS));
+            return;
+        }
+
+        let table = match Table::load(path) {
+            Ok(table) => table,
+            Err(err) => {
+                self.toasts.push((err, TOAST_SECONDS));
+                return;
+            }
+        };
+        self.config.rules = table.rules.clone();
+        self.table = table;
+        self.card_animations.clear();
+        self.hole_card_flip = None;
+        self.dealer_draw_elapsed = 0.0;
+        self.bust_flash = None;
+        self.hands_remaining = None;
+        self.quip = None;
+        self.toasts.push(("Game loaded".to_string(), TOAST_SECONDS));
+    }
+
+    /// Turns the hole card over, then plays out the dealer's hand a card at
+    /// a time. The flip stays at its end until the round is settled.
+    fn exec_game_player_stopped_taking_cards(&mut self, dt: f32) {
+        let progress = self.hole_card_flip.unwrap_or(0.0) + dt / HOLE_CARD_FLIP_SECONDS;
+        if progress < 1.0 {
+            self.hole_card_flip = Some(progress);
+            return;
+        }
+        self.hole_card_flip = Some(1.0);
+
+        self.dealer_draw_elapsed += dt;
+        if self.dealer_draw_elapsed < DEALER_DRAW_SECONDS {
+            return;
+        }
+        self.dealer_draw_elapsed = 0.0;
+
+        self.table.dealer_step();
+        if let GameStatus::GameOver(_) = self.table.status {
+            self.hole_card_flip = None;
+            if self.table.casino_hand.is_bust() {
+                self.start_bust_flash(None);
+            }
+        }
+    }
+
+    /// Shows how the session went once nobody can cover a bet, and offers
+    /// to start over or quit.
+    fn exec_game_bankrupt(&mut self, keycodes: &Vec<Keycode>) {
+        self.render_outcome(OUT_OF_CHIPS_TEXT);
+        self.render_text(&format!("Hands played: {}", self.table.hands_played), 0, 290, 50);
+        self.render_text(&format!("Peak bankroll: {}", self.table.peak_bankroll), 0, 350, 50);
+
+        self.canvas.copy(
+            &self.texture_manager.load_text(&key_prompt(self.bindings.restart(), START_OVER_OR_QUIT_TEXT)), None, 
+            Rect::new(0, self.layout_height as i32 - 80, self.layout_width, 80)).unwrap();
+
+        if keycodes.contains(&self.bindings.restart()) {
+            self.act(ReplayEvent::Restart(self.config.betting.starting_bankroll));
+            self.table.current_bet = self.config.betting.min_bet;
+            self.quip = None;
+        } else if keycodes.contains(&Keycode::Q) {
+            self.quit = true;
+        }
+    }
+
+    fn render_outcome(&mut self, text: &str) {
+        let width = self.layout_width;
+        let height = self.layout_height;
+
+        let rect = match self.config.ui.outcome_style {
+            OutcomeStyle::Bottom => Rect::new(0, height as i32 - 160, width, 80),
+            OutcomeStyle::Center => {
+                let query = self.texture_manager.load_dynamic_text(text).query();
+                let mut banner_height = self.config.ui.outcome_banner_height;
+                let mut banner_width = query.width * banner_height / query.height;
+                if banner_width > width {
+                    banner_height = banner_height * width / banner_width;
+                    banner_width = width;
+                }
+
+                Rect::new(
+                    (width - banner_width) as i32 / 2, (height - banner_height) as i32 / 2,
+                    banner_width, banner_height)
+            }
+        };
+
+        self.canvas.copy(&self.texture_manager.load_dynamic_text(text), None, rect).unwrap();
+    }
+
+    /// Shows each notice for a few seconds.
+    fn render_toasts(&mut self, dt: f32) {
+        for toast in &mut self.toasts {
+            toast.1 -= dt;
         }
+        self.toasts.retain(|(_, remaining)| *remaining > 0.0);
 
-        if casino_score > TWENTY_ONE {
-            self.status = GameStatus::GameOver(Winner::Player);
-        } else if casino_score > player_score {
-            self.status = GameStatus::GameOver(Winner::Casino);
-        } else if casino_score < player_score {
-            self.status = GameStatus::GameOver(Winner::Player);
+        let toasts: Vec<String> = self.toasts.iter().map(|(text, _)| text.clone()).collect();
+        for (idx, toast) in toasts.iter().enumerate() {
+            self.render_text(toast, 0, 340 - idx as i32 * 45, 40);
+        }
+    }
+
+    fn render_quip(&mut self) {
+        let quip = match &self.quip {
+            Some(quip) => quip.clone(),
+            None => return,
+        };
+
+        self.render_text(&quip, 0, 230, 50);
+    }
+
+    /// Draws `text` at its natural aspect ratio, scaled to `height` pixels.
+    fn render_text(&mut self, text: &str, x: i32, y: i32, height: u32) {
+        let texture = self.texture_manager.load_dynamic_text(text);
+        let query = texture.query();
+        let width = query.width * height / query.height;
+        self.canvas.copy(&texture, None, Rect::new(x, y, width, height)).unwrap();
+    }
+
+    fn render_bankroll(&mut self) -> Result<(), String> {
+        let x = self.layout_width as i32 - 350;
+        let bet = if self.table.player_hands.is_empty() {
+            self.table.current_bet
         } else {
-            self.status = GameStatus::GameOver(Winner::Tie);
+            self.table.player_hands.iter().map(|hand| hand.bet).sum()
+        };
+
+        let bankrolls: Vec<String> = self.table.bankrolls.iter().map(|bankroll| bankroll.to_string()).collect();
+        self.render_text(&format!("Bankroll: {}", bankrolls.join(" / ")), x, 0, 50);
+        self.render_text(&format!("Bet: {}", bet), x, 50, 50);
+        if self.table.insurance_bet > 0 {
+            self.render_text(&format!("Insurance: {}", self.table.insurance_bet), x, 100, 50);
+        }
+
+        let record = format!("W {} / L {} / T {}", self.stats.player_wins, self.stats.casino_wins, self.stats.ties);
+        self.render_text(&record, x, 160, 40);
+        if self.show_count {
+            let count = format!("Count: {} (true {:.1})", self.table.running_count, self.table.true_count());
+            self.render_text(&count, x, 200, 40);
+        }
+
+        let remaining = self.table.cards_remaining();
+        let shoe = format!("Shoe: {}/{} ({}%)", remaining, self.table.deck.len(), remaining * 100 / self.table.deck.len());
+        self.render_text(&shoe, x, 240, 30);
+        if let Some(hands) = self.hands_remaining {
+            self.render_text(&format!("Hands left: {}", hands), x, 280, 30);
         }
+
+        if self.table.bankrolls.len() == 1 {
+            self.render_chips(self.table.bankrolls[0], x, 370)?;
+        }
+        self.render_chips(bet, x, 470)?;
+
+        return Ok(());
+    }
+
+    /// Draws `amount` as one stack of chips per denomination, bottom edge at `y`.
+    fn render_chips(&mut self, amount: u32, x: i32, y: i32) -> Result<(), String> {
+        let chips = chips_for_amount(amount);
+        for (column, denom) in ChipDenom::iterator().enumerate() {
+            let count = chips.iter().filter(|chip| **chip == denom).count().min(MAX_CHIPS_PER_STACK);
+            let chip_x = x + column as i32 * (CHIP_SIZE as i32 + 10);
+            for idx in 0..count {
+                let texture = self.texture_manager.load_texture(&denom.texture_path())?;
+                let chip_y = y - CHIP_SIZE as i32 - idx as i32 * CHIP_STEP;
+                self.canvas.copy(&texture, None, Rect::new(chip_x, chip_y, CHIP_SIZE, CHIP_SIZE)).unwrap();
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Frame rate, frame time and texture count in the top left corner,
+    /// over everything else.
+    fn render_diagnostics(&mut self) {
+        let fps = if self.smoothed_dt > 0.0 { 1.0 / self.smoothed_dt } else { 0.0 };
+        let text = format!("{:.0} FPS  {:.1} ms  {} textures", fps, self.smoothed_dt * 1000.0, self.texture_manager.cached_count());
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 180));
+        self.canvas.fill_rect(Rect::new(0, 0, 520, 34)).unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+        self.render_text(&text, 5, 3, 28);
     }
 
-    fn render_hands(&mut self) {
-        for (idx, card) in (&self.casino_hand).into_iter().enumerate() {
-            let text_path = &self.deck[*card].path;
-            let text = self.texture_manager.load_texture(&text_path);
-            self.canvas.copy(&text, None, Rect::new(0 + (idx as i32 * 100), 0, 100, 150)).unwrap();
+    /// The cards played since the last shuffle as a stack of backs on the
+    /// left of the table, one more back for each share of the shoe used up.
+    fn render_discard_pile(&mut self) -> Result<(), String> {
+        let discarded = self.table.cards_discarded();
+        let slots = self.config.ui.discard_slots as usize;
+        if discarded == 0 || slots == 0 {
+            return Ok(());
         }
 
-        for (idx, card) in (&self.player_hand).into_iter().enumerate() {
-            let text_path = &self.deck[*card].path;
-            let text = self.texture_manager.load_texture(&text_path);
-            self.canvas.copy(&text, None, Rect::new(0 + (idx as i32 * 100), 500,100, 150)).unwrap();
+        let shoe_size = self.table.deck.len();
+        let backs = (discarded * slots).div_ceil(shoe_size).min(slots);
+        let back = self.texture_manager.load_texture(&self.card_art_path(CARD_BACK_PATH))?;
+        for idx in 0..backs {
+            let y = DISCARD_PILE_Y - idx as i32 * DISCARD_STEP;
+            self.canvas.copy(&back, None, Rect::new(20, y, CARD_WIDTH, CARD_HEIGHT)).unwrap();
         }
+        let label_x = 30 + CARD_WIDTH as i32;
+        self.render_text(&format!("Discards: {}", discarded), label_x, DISCARD_PILE_Y + CARD_HEIGHT as i32 / 2, 26);
+
+        return Ok(());
     }
 
-    fn get_random_card(&mut self) -> Option<usize> {
-        if self.deck.len() <= self.used_cards.len() {
-            return None;
+    /// The latest lines of the round's history on a dark panel under the
+    /// bankroll, older lines scrolling off the top.
+    fn render_history(&mut self) {
+        let x = self.layout_width as i32 - 350;
+        let y = 280;
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 150));
+        self.canvas.fill_rect(Rect::new(x, y, 350, HISTORY_LINES as u32 * HISTORY_LINE_HEIGHT + 10)).unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+
+        let first = self.table.history.len().saturating_sub(HISTORY_LINES);
+        let lines: Vec<String> = self.table.history[first..].to_vec();
+        for (idx, line) in lines.iter().enumerate() {
+            self.render_text(line, x + 10, y + 5 + (idx as u32 * HISTORY_LINE_HEIGHT) as i32, HISTORY_LINE_HEIGHT - 4);
         }
-    
-        let mut rng = rand::thread_rng();
-        let mut index = rng.gen_range(0..self.deck.len());
-    
-        while self.used_cards.contains(&index) {
-            index = rng.gen_range(0..self.deck.len());
+    }
+
+    /// Whether a round is being played, rather than a menu or an end screen shown.
+    fn at_table(&self) -> bool {
+        return !matches!(self.table.status,
+            GameStatus::MainMenu | GameStatus::Settings | GameStatus::Achievements | GameStatus::Stats
+                | GameStatus::TournamentOver | GameStatus::Bankrupt);
+    }
+
+    /// The felt, with a betting circle where each hand goes, or a plain
+    /// fill of the table colour when there's no felt image.
+    fn render_table(&mut self) {
+        let (r, g, b) = self.config.theme.table_color;
+        self.canvas.set_draw_color(Color::RGB(r, g, b));
+        self.canvas.clear();
+        if self.config.theme.felt_path.is_empty() || self.felt_missing {
+            return;
+        }
+
+        let felt = match self.texture_manager.load_texture(&self.config.theme.felt_path) {
+            Ok(felt) => felt,
+            Err(err) => {
+                warn!("{}, using the plain table colour", err);
+                self.felt_missing = true;
+                return;
+            }
+        };
+        self.canvas.copy(&felt, None, None).unwrap();
+
+        let at_table = self.at_table();
+        let circle = match self.texture_manager.load_texture(BET_CIRCLE_PATH) {
+            Ok(circle) if at_table => circle,
+            _ => return,
+        };
+        // Before the deal there's a spot for each player.
+        let spots = if self.table.player_hands.is_empty() {
+            self.table.bankrolls.len()
+        } else {
+            self.table.player_hands.len()
+        };
+        let size = CARD_HEIGHT + 2 * BET_CIRCLE_MARGIN;
+        for spot in 0..spots {
+            let x = (self.layout_width as usize / spots * spot) as i32 + CARD_WIDTH as i32 / 2 - size as i32 / 2;
+            let rect = Rect::new(x.max(0), 500 - BET_CIRCLE_MARGIN as i32, size, size);
+            self.canvas.copy(&circle, None, rect).unwrap();
+        }
+    }
+
+    /// Left edge of the screen region given to player hand `idx`.
+    fn player_hand_x(&self, idx: usize) -> i32 {
+        return (self.layout_width as usize / self.table.player_hands.len() * idx) as i32;
+    }
+
+    fn render_totals(&mut self) {
+        // Softness would give away the hole card, so only the upcard's
+        // plain value shows until the reveal.
+        let casino_total = if self.table.status.hole_card_hidden() {
+            self.table.dealer_visible_score().to_string()
+        } else {
+            self.table.casino_hand.value().label()
+        };
+
+        self.render_text(&format!("Dealer: {}", casino_total), 0, 0, 50);
+
+        for idx in 0..self.table.player_hands.len() {
+            let player_total = self.table.player_hands[idx].cards.value().label();
+            let text = if self.table.player_hands.len() == 1 {
+                format!("Player: {}", player_total)
+            } else if self.table.bankrolls.len() > 1 {
+                format!("Player {}: {}", self.table.player_hands[idx].seat + 1, player_total)
+            } else {
+                format!("Hand {}: {}", idx + 1, player_total)
+            };
+
+            self.render_text(&text, self.player_hand_x(idx), 660, 50);
         }
-    
-        self.used_cards.push(index);
-        
-        return Some(index);
     }
 
-    fn calculate_hand_score(&self, hand: &Vec<usize>) -> usize {
-        let mut result = 0;
-        for card in hand {
-            let card_score = self.deck[*card].card_type.get_score();
-            result += card_score;
+    fn advance_card_animations(&mut self, dt: f32) {
+        let table = &self.table;
+        self.card_animations.retain(|card, _| {
+            table.casino_hand.contains(*card) || table.player_hands.iter().any(|hand| hand.cards.contains(*card))
+        });
+
+        for animation in self.card_animations.values_mut() {
+            animation.progress = (animation.progress + dt / DEAL_ANIMATION_SECONDS).min(1.0);
         }
-    
-        return result;
+    }
+
+    /// The theme's art for `path`, or the default deck's where it has none.
+    fn card_art_path(&self, path: &str) -> String {
+        return self.card_art.get(path).cloned().unwrap_or(path.to_string());
+    }
+
+    /// Draws `card` at `(x, y)`, or on its way there if it was just dealt.
+    /// `width_scale` squeezes the card about its middle, as when turning it over.
+    fn render_card(&mut self, card: usize, face_down: bool, x: i32, y: i32, width_scale: f32) -> Result<(), String> {
+        let shoe_position = (self.layout_width as i32 - CARD_WIDTH as i32, -(CARD_HEIGHT as i32));
+        if !self.card_animations.contains_key(&card) {
+            self.play_sound(Sound::Deal);
+        }
+        let animation = self.card_animations.entry(card)
+            .or_insert(CardAnimation { start: shoe_position, progress: 0.0 });
+        let (start_x, start_y) = animation.start;
+        let x = start_x + ((x - start_x) as f32 * animation.progress) as i32;
+        let y = start_y + ((y - start_y) as f32 * animation.progress) as i32;
+
+        let path = if face_down {
+            self.card_art_path(CARD_BACK_PATH)
+        } else {
+            self.card_art_path(&self.table.deck[card].path)
+        };
+        let width = (CARD_WIDTH as f32 * width_scale) as u32;
+        let text = self.texture_manager.load_texture(&path)?;
+        self.canvas.copy(&text, None, Rect::new(x + (CARD_WIDTH - width) as i32 / 2, y, width, CARD_HEIGHT)).unwrap();
+
+        if self.config.ui.colorblind_mode && !face_down && width == CARD_WIDTH {
+            let badge = Rect::new(x + (CARD_WIDTH - SUIT_BADGE_SIZE) as i32 - 4, y + 4, SUIT_BADGE_SIZE, SUIT_BADGE_SIZE);
+            self.canvas.set_draw_color(Color::RGB(20, 20, 20));
+            self.canvas.fill_rect(badge).unwrap();
+            let letter = self.texture_manager.load_text(self.table.deck[card].card_suit.get_letter());
+            let width = letter.query().width * SUIT_BADGE_SIZE / letter.query().height;
+            let letter_x = badge.x() + (SUIT_BADGE_SIZE as i32 - width as i32) / 2;
+            self.canvas.copy(&letter, None, Rect::new(letter_x, badge.y(), width, SUIT_BADGE_SIZE)).unwrap();
+        }
+
+        return Ok(());
+    }
+
+    /// Draws a border around a hand of `cards` cards laid out from `(x, y)`
+    /// within `available_width`.
+    fn outline_hand(&mut self, x: i32, y: i32, cards: usize, available_width: i32, color: Color) {
+        let width = (cards as i32 - 1) * card_step(cards, available_width) + CARD_WIDTH as i32;
+        self.canvas.set_draw_color(color);
+        self.canvas.draw_rect(Rect::new(x - 5, y - 5, width as u32 + 10, CARD_HEIGHT + 10)).unwrap();
+    }
+
+    fn render_hands(&mut self) -> Result<(), String> {
+        let hole_card_hidden = self.table.status.hole_card_hidden();
+        // The dealer's cards share the top row with the bankroll panel.
+        let casino_step = card_step(self.table.casino_hand.len(), self.layout_width as i32 - 350);
+        for idx in 0..self.table.casino_hand.len() {
+            let card = self.table.casino_hand.cards()[idx].id;
+            // The back narrows to nothing, then the face widens back out.
+            let (face_down, width_scale) = match self.hole_card_flip {
+                Some(progress) if idx == 1 => (progress < 0.5, (1.0 - 2.0 * progress).abs()),
+                _ => (idx == 1 && hole_card_hidden, 1.0),
+            };
+            self.render_card(card, face_down, idx as i32 * casino_step, 60, width_scale)?;
+        }
+
+        let hand_width = self.layout_width as i32 / self.table.player_hands.len().max(1) as i32;
+        for hand in 0..self.table.player_hands.len() {
+            let hand_x = self.player_hand_x(hand);
+            let step = card_step(self.table.player_hands[hand].cards.len(), hand_width);
+            for idx in 0..self.table.player_hands[hand].cards.len() {
+                let card = self.table.player_hands[hand].cards.cards()[idx].id;
+                self.render_card(card, false, hand_x + (idx as i32 * step), 500, 1.0)?;
+            }
+        }
+
+        if self.table.player_hands.len() > 1 && hole_card_hidden {
+            let hand_x = self.player_hand_x(self.table.active_hand);
+            let cards = self.table.player_hands[self.table.active_hand].cards.len();
+            self.outline_hand(hand_x, 500, cards, hand_width, ACTIVE_HAND_COLOR);
+        }
+
+        // Winning hands get a border at game over; a push outlines nothing.
+        if let GameStatus::GameOver(results) = &self.table.status {
+            let casino_won = overall_winner(results) == Winner::Casino;
+            let winning_hands: Vec<usize> = results.iter().enumerate()
+                .filter(|(_, result)| result.winner == Winner::Player)
+                .map(|(hand, _)| hand)
+                .collect();
+
+            if casino_won {
+                let cards = self.table.casino_hand.len();
+                self.outline_hand(0, 60, cards, self.layout_width as i32 - 350, WINNER_COLOR);
+            }
+            for hand in winning_hands {
+                let cards = self.table.player_hands[hand].cards.len();
+                self.outline_hand(self.player_hand_x(hand), 500, cards, hand_width, WINNER_COLOR);
+            }
+        }
+
+        if !self.table.player_hands.is_empty() {
+            self.render_totals();
+        }
+
+        return Ok(());
     }
 }
 
 pub fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Fails with every missing card image at once, rather than crashing the
+/// first time one of them is dealt. The default set has to be complete, as
+/// themes fall back on it.
+fn check_card_assets(cards_dir: &str) -> Result<(), String> {
+    if !Path::new(cards_dir).is_dir() {
+        return Err(format!("theme.cards_dir: `{}` is not a directory", cards_dir));
+    }
+
+    let mut missing = missing_card_assets(&get_deck(1));
+    if !Path::new(CARD_BACK_PATH).exists() {
+        missing.push(CARD_BACK_PATH.to_string());
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let list: Vec<String> = missing.iter().map(|path| format!("  {}", path)).collect();
+    return Err(format!("missing {} card image(s):\n{}", missing.len(), list.join("\n")));
+}
+
+fn run() -> Result<(), String> {
+    // Only warnings unless asked for, e.g. RUST_LOG=debug for every round's hands.
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    let cli = parse_args(std::env::args().skip(1).collect())?;
+    let mut config = Config::load(CONFIG_PATH)?;
+    cli.apply(&mut config)?;
+
+    let playback = match &cli.replay {
+        Some(path) => Some(Replay::load(path)?),
+        None => None,
+    };
+    let seed = match (&playback, cli.seed) {
+        (Some(replay), _) => replay.seed,
+        (None, Some(seed)) => seed,
+        (None, None) => rand::random::<u64>(),
+    };
+    info!("seed: {} (pass --seed {} to replay this game)", seed, seed);
+
+    if let Some(rounds) = cli.simulate {
+        print_simulation(&config, rounds, seed);
+        return Ok(());
+    }
+
+    check_card_assets(&config.theme.cards_dir)?;
+
+    let sdl_context = sdl2::init().map_err(|err| format!("failed to initialize SDL: {}", err))?;
+    let video_subsystem = sdl_context.video().map_err(|err| format!("failed to initialize video: {}", err))?;
  
-    let window = video_subsystem.window(WIN_NAME, WIDTH, HEIGHT)
-        .position_centered()
-        .build()
-        .unwrap();
+    let mut window_builder = video_subsystem.window(WIN_NAME, config.window.width, config.window.height);
+    window_builder.position_centered().resizable();
+    if config.window.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build()
+        .map_err(|err| format!("failed to create window: {}", err))?;
 
-    let ttf_context = sdl2::ttf::init().unwrap();
-    let canvas = window.into_canvas().build().unwrap();
-    let texture_creator = canvas.texture_creator();
-    let deck = get_deck();
-    let mut texture_manager = TextureManager::new(&texture_creator);
+    let quips = if config.ui.dealer_quips {
+        Quips::load(&config.ui.quips_path)?
+    } else {
+        Quips::default()
+    };
 
-    init_font_textures(&ttf_context, &mut texture_manager);
+    let ttf_context = sdl2::ttf::init().map_err(|err| format!("failed to initialize fonts: {}", err))?;
+    let canvas = window.into_canvas().build().map_err(|err| format!("failed to create canvas: {}", err))?;
+    let texture_creator = canvas.texture_creator();
+    let font = ttf_context
+        .load_font(FONT_PATH, 128)
+        .map_err(|err| format!("failed to load font {}: {}", FONT_PATH, err))?
+    ;
+    let (r, g, b) = config.theme.text_color;
+    let texture_manager = TextureManager::new(&texture_creator, font, Color::RGB(r, g, b));
 
-    let mut game = Game::new(deck, canvas, texture_manager);
-    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut table = Table::new(config.rules.clone(), config.betting.starting_bankroll, config.betting.min_bet, seed);
+    if let Some(replay) = &playback {
+        table = replay.table();
+        config.rules = replay.rules.clone();
+        config.betting.starting_bankroll = replay.bankroll;
+        // The recorded hits already include any the game made by itself.
+        config.ui.auto_hit_safe_hands = false;
+    }
+    let recording = match cli.record {
+        Some(_) => Some(Replay::new(seed, config.betting.starting_bankroll, config.rules.clone())),
+        None => None,
+    };
+    let stats = Stats::load(STATS_PATH)?;
+    // The game is playable without audio, so a missing sound device is not fatal.
+    let sounds = match Sounds::load() {
+        Ok(sounds) => Some(sounds),
+        Err(err) => {
+            warn!("{}, playing without sound", err);
+            None
+        }
+    };
+    let mut game = Game::new(table, canvas, texture_manager, config, quips, stats, sounds)?;
+    game.recording = recording;
+    game.playback = playback.map(|replay| (replay, 0));
+    game.leaderboard = Leaderboard::load(LEADERBOARD_PATH)?;
+    game.debug_keys = cli.debug;
+    if cli.demo {
+        game.demo = true;
+        game.table.status = GameStatus::AwaitingBet;
+    }
+    let mut event_pump = sdl_context.event_pump()?;
+    let frame_time = Duration::from_secs(1) / MAX_FPS;
+    let mut last_frame = Instant::now();
     'running: loop {
+        let frame_start = Instant::now();
+        let dt = frame_start.duration_since(last_frame).as_secs_f32();
+        last_frame = frame_start;
+
         let mut pressed_keycodes = Vec::<Keycode>::new();
+        let mut clicks = Vec::<(i32, i32)>::new();
         for event in event_pump.poll_iter() {
             match event {
-                Event::Quit {..} |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                Event::Quit {..} => {
                     break 'running
                 },
-                Event::KeyDown { keycode: Some(keycode), .. } => {
+                // Held keys keep sending repeat events; only the press itself counts.
+                Event::KeyDown { keycode: Some(keycode), repeat: false, .. } => {
                     pressed_keycodes.push(keycode);
                 },
+                Event::Window { win_event: WindowEvent::SizeChanged(..), .. } => {
+                    game.fit_layout_to_window();
+                },
+                Event::MouseMotion { x, y, .. } => {
+                    game.mouse_position = (x, y);
+                },
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    clicks.push((x, y));
+                },
                 _ => {}
             }
         }
 
-        game.exec_cycle(&pressed_keycodes);
+        game.exec_cycle(&pressed_keycodes, &clicks, dt)?;
+        if game.quit {
+            break 'running;
+        }
+
+        // Only sleep off what's left of the frame after rendering it.
+        if let Some(remaining) = frame_time.checked_sub(frame_start.elapsed()) {
+            ::std::thread::sleep(remaining);
+        }
+    }
+
+    game.stats.save(STATS_PATH)?;
+    if let (Some(path), Some(recording)) = (&cli.record, &game.recording) {
+        recording.save(path)?;
+    }
+
+    return Ok(());
+}
+
+/// Command line overrides for the config file.
+#[derive(Debug, PartialEq, Default)]
+struct CliArgs {
+    seed: Option<u64>,
+    decks: Option<usize>,
+    bankroll: Option<u32>,
+    players: Option<usize>,
+    fullscreen: bool,
+    mute: bool,
+    /// Start straight into the basic strategy demo.
+    demo: bool,
+    /// Turns on the testing keys, such as F5 to redeal.
+    debug: bool,
+    simulate: Option<u64>,
+    record: Option<String>,
+    replay: Option<String>
+}
 
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+impl CliArgs {
+    fn apply(&self, config: &mut Config) -> Result<(), String> {
+        if let Some(decks) = self.decks {
+            config.rules.decks = decks;
+        }
+        if let Some(bankroll) = self.bankroll {
+            config.betting.starting_bankroll = bankroll;
+        }
+        if let Some(players) = self.players {
+            config.rules.players = players;
+        }
+        if self.fullscreen {
+            config.window.fullscreen = true;
+        }
+        if self.mute {
+            config.ui.mute = true;
+        }
+
+        return config.validate();
     }
 }
 
-fn init_font_textures(ttf_context: &Sdl2TtfContext, texture_manager: &mut TextureManager) {
-    let font = ttf_context
-        .load_font("./assets/fonts/opensans/OpenSans-Regular.ttf", 128)
-        .unwrap()
-    ;
+/// Plays `rounds` rounds of basic strategy at the minimum bet and prints
+/// how it went.
+fn print_simulation(config: &Config, rounds: u64, seed: u64) {
+    let report = simulate(config.rules.clone(), config.betting.min_bet, rounds, seed, suggest);
+    let percent = |count: u64| count as f64 * 100.0 / rounds.max(1) as f64;
 
-    for str in [
-        TAKE_ANOTHER_CARD_TEXT, PLAYER_WINS_TEXT, 
-        CASINO_WINS_TEXT, ITS_A_TIE_TEXT, 
-        N_TO_RESTART_THE_GAME, STOP_TAKING_CARDS_TEXT] {
-        let surface = font
-            .render(str)
-            .blended(Color::RGB(255, 255, 255))
-            .unwrap()
-        ;
+    println!("rounds: {}", report.rounds);
+    println!("player wins: {} ({:.2}%)", report.player_wins, report.win_rate() * 100.0);
+    println!("casino wins: {} ({:.2}%)", report.casino_wins, percent(report.casino_wins));
+    println!("ties: {} ({:.2}%)", report.ties, percent(report.ties));
+    println!("house edge: {:.2}%", report.house_edge() * 100.0);
+    let trajectory: Vec<String> = report.trajectory.iter().map(|net| net.to_string()).collect();
+    println!("net winnings by tenth: {}", trajectory.join(", "));
+}
 
-        texture_manager.load_texture_from_surface(str, surface);
+/// Moves a menu `selection` among `len` items with the Up and Down keys,
+/// wrapping around at either end.
+fn menu_step(selection: usize, len: usize, keycodes: &Vec<Keycode>) -> usize {
+    if keycodes.contains(&Keycode::Up) {
+        return (selection + len - 1) % len;
+    } else if keycodes.contains(&Keycode::Down) {
+        return (selection + 1) % len;
     }
+
+    return selection;
 }
 
-fn get_deck() -> Vec::<Card> {
-    let mut vec = Vec::<Card>::new();
-    for tp in CardType::iterator() {
-        for suit in CardSuit::iterator() {
-            let texture_path = tp.get_string_name() + "_of_" + suit.get_string_name().as_str() + ".png";
-            vec.push(Card { card_type: tp, _card_suit: suit, path: "assets/cards/".to_owned() + texture_path.as_str() })
+fn parse_args(args: Vec<String>) -> Result<CliArgs, String> {
+    let mut args = args.into_iter();
+    let mut cli = CliArgs::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => cli.seed = Some(parse_flag_value(&arg, args.next())?),
+            "--decks" => cli.decks = Some(parse_flag_value(&arg, args.next())?),
+            "--bankroll" => cli.bankroll = Some(parse_flag_value(&arg, args.next())?),
+            "--players" => cli.players = Some(parse_flag_value(&arg, args.next())?),
+            "--fullscreen" => cli.fullscreen = true,
+            "--mute" => cli.mute = true,
+            "--demo" => cli.demo = true,
+            "--debug" => cli.debug = true,
+            "--simulate" => cli.simulate = Some(parse_flag_value(&arg, args.next())?),
+            "--record" => cli.record = Some(parse_flag_value(&arg, args.next())?),
+            "--replay" => cli.replay = Some(parse_flag_value(&arg, args.next())?),
+            _ => return Err(format!("unknown argument `{}`\n{}", arg, USAGE)),
         }
     }
 
-    return vec
-}
\ No newline at end of file
+    return Ok(cli);
+}
+
+fn parse_flag_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value\n{}", flag, USAGE))?;
+    return value.parse::<T>().map_err(|_| format!("invalid value `{}` for {}", value, flag));
+}
+
+/// The first of the requested decisions, so keys pressed together in one
+/// frame still make a single move. Whatever it does to the table, the
+/// rest wait for the next frame's status.
+fn pick_decision(requested: &[(bool, ReplayEvent)]) -> Option<ReplayEvent> {
+    return requested.iter().find(|(pressed, _)| *pressed).map(|(_, event)| *event);
+}
+
+/// Where to load each default card image from with the theme in
+/// `cards_dir`: the themed file of the same name where there is one,
+/// otherwise the default.
+fn themed_card_art(cards_dir: &str) -> HashMap<String, String> {
+    let mut art = HashMap::<String, String>::new();
+    let defaults = get_deck(1).into_iter().map(|card| card.path).chain([CARD_BACK_PATH.to_string()]);
+    for path in defaults {
+        let file_name = Path::new(&path).file_name().unwrap();
+        let themed = Path::new(cards_dir).join(file_name);
+        if themed.exists() {
+            art.insert(path, themed.to_string_lossy().to_string());
+        } else {
+            debug!("{} missing from the card theme, using the default", file_name.to_string_lossy());
+            art.insert(path.clone(), path);
+        }
+    }
+
+    return art;
+}
+
+/// "Press <key> <action>", naming whichever key is bound to the action.
+fn key_prompt(key: Keycode, action: &str) -> String {
+    return format!("Press {} {}", key.name(), action);
+}
+
+fn tie_explanation_text(score: usize) -> String {
+    return format!("Push — both {}", score);
+}
+
+/// Horizontal distance between neighbouring cards of a hand. Cards sit side
+/// by side while they fit in `available_width` and overlap more and more as
+/// the hand grows, never hiding the rank in the left corner.
+fn card_step(card_count: usize, available_width: i32) -> i32 {
+    let full_step = CARD_WIDTH as i32;
+    if card_count < 2 {
+        return full_step;
+    }
+
+    let step = (available_width - full_step) / (card_count as i32 - 1);
+    return step.min(full_step).max(MIN_CARD_STEP);
+}
+
+fn quip_situation(results: &[RoundResult]) -> QuipSituation {
+    if results.len() > 1 {
+        return match overall_winner(results) {
+            Winner::Player => QuipSituation::PlayerWins,
+            Winner::Casino => QuipSituation::CasinoWins,
+            Winner::Tie => QuipSituation::Tie,
+        };
+    }
+
+    return match results[0].reason {
+        RoundReason::PlayerBust => QuipSituation::PlayerBust,
+        RoundReason::DealerBust => QuipSituation::DealerBust,
+        RoundReason::PlayerBlackjack | RoundReason::PlayerHigher
+            | RoundReason::FiveCardCharlie | RoundReason::EvenMoney => QuipSituation::PlayerWins,
+        RoundReason::DealerBlackjack | RoundReason::DealerHigher | RoundReason::DealerWinsTie
+            | RoundReason::Surrender => QuipSituation::CasinoWins,
+        RoundReason::Push => QuipSituation::Tie,
+    };
+}
+
+fn number_key_value(keycode: Keycode) -> Option<u32> {
+    return match keycode {
+        Keycode::Num1 | Keycode::Kp1 => Some(1),
+        Keycode::Num2 | Keycode::Kp2 => Some(2),
+        Keycode::Num3 | Keycode::Kp3 => Some(3),
+        Keycode::Num4 | Keycode::Kp4 => Some(4),
+        Keycode::Num5 | Keycode::Kp5 => Some(5),
+        Keycode::Num6 | Keycode::Kp6 => Some(6),
+        Keycode::Num7 | Keycode::Kp7 => Some(7),
+        Keycode::Num8 | Keycode::Kp8 => Some(8),
+        Keycode::Num9 | Keycode::Kp9 => Some(9),
+        _ => None,
+    };
+}
+
+/// The letter typed with `keycode`, in upper case.
+fn letter_key_value(keycode: Keycode) -> Option<char> {
+    let code = keycode as i32;
+    if code < Keycode::A as i32 || code > Keycode::Z as i32 {
+        return None;
+    }
+
+    return Some((code as u8 as char).to_ascii_uppercase());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cards_overlap_only_when_the_hand_does_not_fit() {
+        assert_eq!(card_step(5, 1200), 100);
+        assert_eq!(card_step(11, 600), 50);
+        assert_eq!(card_step(40, 600), MIN_CARD_STEP);
+    }
+
+    #[test]
+    fn themed_cards_fall_back_to_the_default_set() {
+        let dir = std::env::temp_dir().join("blackjack_card_theme_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ace_of_spades.png"), "").unwrap();
+
+        let art = themed_card_art(dir.to_str().unwrap());
+        assert_eq!(art.len(), 53);
+        assert_eq!(art["assets/cards/ace_of_spades.png"], dir.join("ace_of_spades.png").to_string_lossy());
+        assert_eq!(art["assets/cards/ace_of_hearts.png"], "assets/cards/ace_of_hearts.png");
+        assert_eq!(art[CARD_BACK_PATH], CARD_BACK_PATH);
+    }
+
+    #[test]
+    fn keys_pressed_together_make_one_decision() {
+        let stand_and_hit = [(true, ReplayEvent::Stand), (true, ReplayEvent::Hit), (false, ReplayEvent::DoubleDown)];
+        assert_eq!(pick_decision(&stand_and_hit), Some(ReplayEvent::Stand));
+        assert_eq!(pick_decision(&[(false, ReplayEvent::Stand), (true, ReplayEvent::Hit)]), Some(ReplayEvent::Hit));
+        assert_eq!(pick_decision(&[(false, ReplayEvent::Stand)]), None);
+    }
+
+    #[test]
+    fn overrides_are_read_from_the_command_line() {
+        let args = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()).collect());
+
+        assert_eq!(args(&[]), Ok(CliArgs::default()));
+        assert_eq!(args(&["--seed", "42", "--decks", "6", "--bankroll", "500", "--players", "2", "--fullscreen"]), Ok(CliArgs {
+            seed: Some(42), decks: Some(6), bankroll: Some(500), players: Some(2), fullscreen: true, mute: false, demo: false,
+            debug: false, simulate: None, record: None, replay: None
+        }));
+        assert!(args(&["--seed"]).is_err());
+        assert!(args(&["--decks", "x"]).is_err());
+        assert!(args(&["--speed"]).is_err());
+    }
+}
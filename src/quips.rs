@@ -0,0 +1,44 @@
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use std::fs;
+
+#[derive(Clone, Copy)]
+pub enum QuipSituation {
+    PlayerBust,
+    DealerBust,
+    PlayerWins,
+    CasinoWins,
+    Tie
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Quips {
+    player_bust: Vec<String>,
+    dealer_bust: Vec<String>,
+    player_wins: Vec<String>,
+    casino_wins: Vec<String>,
+    tie: Vec<String>
+}
+
+impl Quips {
+    pub fn load(path: &str) -> Result<Quips, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read dealer quips {}: {}", path, err))?;
+
+        return toml::from_str(&content)
+            .map_err(|err: toml::de::Error| format!("invalid dealer quips {}: {}", path, err.message()));
+    }
+
+    pub fn pick(&self, situation: QuipSituation) -> Option<&String> {
+        let phrases = match situation {
+            QuipSituation::PlayerBust => &self.player_bust,
+            QuipSituation::DealerBust => &self.dealer_bust,
+            QuipSituation::PlayerWins => &self.player_wins,
+            QuipSituation::CasinoWins => &self.casino_wins,
+            QuipSituation::Tie => &self.tie,
+        };
+
+        return phrases.choose(&mut rand::thread_rng());
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::config::RulesConfig;
+use crate::Table;
+
+/// Something the player did at the table. The cards all follow from the
+/// seed, so the player's choices are everything a replay has to keep.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ReplayEvent {
+    Bet(u32),
+    EarlySurrender(bool),
+    Insurance(bool),
+    EvenMoney(bool),
+    Hit,
+    Stand,
+    DoubleDown,
+    Split,
+    Surrender,
+    NextRound,
+    /// Starting over with every bankroll back at the amount given.
+    Restart(u32),
+    /// The round called off and dealt again, reshuffling first if true.
+    Redeal(bool)
+}
+
+impl ReplayEvent {
+    pub fn apply(&self, table: &mut Table) {
+        match *self {
+            ReplayEvent::Bet(bet) => {
+                table.current_bet = bet;
+                table.place_bet();
+            },
+            ReplayEvent::EarlySurrender(accepted) => table.resolve_early_surrender(accepted),
+            ReplayEvent::Insurance(accepted) => table.resolve_insurance(accepted),
+            ReplayEvent::EvenMoney(accepted) => table.resolve_even_money(accepted),
+            ReplayEvent::Hit => table.hit(),
+            ReplayEvent::Stand => table.stand(),
+            ReplayEvent::DoubleDown => table.double_down(),
+            ReplayEvent::Split => table.split(),
+            ReplayEvent::Surrender => table.surrender(),
+            ReplayEvent::NextRound => table.clear_round(),
+            ReplayEvent::Restart(bankroll) => table.restart(bankroll),
+            ReplayEvent::Redeal(reshuffle) => table.redeal(reshuffle),
+        }
+    }
+}
+
+/// A recorded session: the table it was played at and every choice made
+/// there, in order. Dealing and the dealer's play happen on their own.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub bankroll: u32,
+    pub rules: RulesConfig,
+    pub events: Vec<ReplayEvent>
+}
+
+impl Replay {
+    pub fn new(seed: u64, bankroll: u32, rules: RulesConfig) -> Replay {
+        return Replay {
+            seed: seed,
+            bankroll: bankroll,
+            rules: rules,
+            events: Vec::<ReplayEvent>::new()
+        };
+    }
+
+    /// A fresh table in the state the recording started from, waiting for
+    /// the first bet.
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(self.rules.clone(), self.bankroll, 0, self.seed);
+        table.clear_round();
+
+        return table;
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read replay {}: {}", path, err))?;
+
+        return toml::from_str(&content)
+            .map_err(|err: toml::de::Error| format!("invalid replay {}: {}", path, err.message()));
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|err| format!("failed to write replay {}: {}", path, err))?;
+
+        return fs::write(path, content)
+            .map_err(|err| format!("failed to write replay {}: {}", path, err));
+    }
+}
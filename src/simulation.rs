@@ -0,0 +1,96 @@
+use crate::config::RulesConfig;
+use crate::strategy::playable_action;
+use crate::{overall_winner, Action, GameStatus, Table, Winner};
+
+/// Bankroll the simulated player starts with; large enough that a long
+/// losing run never stops them from covering a bet.
+const SIMULATION_BANKROLL: u32 = u32::MAX / 2;
+/// How many bankroll samples the trajectory keeps over the whole run.
+const TRAJECTORY_POINTS: u64 = 10;
+
+#[derive(Debug, PartialEq)]
+pub struct SimulationReport {
+    pub rounds: u64,
+    pub player_wins: u64,
+    pub casino_wins: u64,
+    pub ties: u64,
+    /// Sum of every initial bet placed.
+    pub wagered: u64,
+    /// Net winnings after each tenth of the run.
+    pub trajectory: Vec<i64>
+}
+
+impl SimulationReport {
+    /// What the casino keeps per unit of initial bet.
+    pub fn house_edge(&self) -> f64 {
+        let net = *self.trajectory.last().unwrap_or(&0);
+        return -(net as f64) / self.wagered.max(1) as f64;
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        return self.player_wins as f64 / self.rounds.max(1) as f64;
+    }
+}
+
+/// Plays `rounds` rounds of flat `bet`s without a window, asking `strategy`
+/// for every decision and always declining insurance and even money. The same `seed` gives
+/// the same report. The simulated player always plays alone.
+pub fn simulate<F: Fn(&Table) -> Action>(rules: RulesConfig, bet: u32, rounds: u64, seed: u64, strategy: F) -> SimulationReport {
+    let mut table = Table::new(RulesConfig { players: 1, ..rules }, SIMULATION_BANKROLL, bet, seed);
+    let mut report = SimulationReport {
+        rounds: rounds,
+        player_wins: 0,
+        casino_wins: 0,
+        ties: 0,
+        wagered: 0,
+        trajectory: Vec::<i64>::new()
+    };
+    let sample_every = (rounds / TRAJECTORY_POINTS).max(1);
+
+    for round in 1..=rounds {
+        table.place_bet();
+        table.deal();
+        report.wagered += bet as u64;
+
+        loop {
+            match table.status {
+                GameStatus::OfferingEarlySurrender => table.resolve_early_surrender(false),
+                GameStatus::OfferingInsurance => table.resolve_insurance(false),
+                GameStatus::OfferingEvenMoney => table.resolve_even_money(false),
+                GameStatus::AwaitingPlayerDecision => {
+                    let action = strategy(&table);
+                    play_action(&mut table, action);
+                },
+                GameStatus::PlayerStopedTakingCards => table.play_dealer(),
+                _ => break,
+            }
+        }
+
+        if let GameStatus::GameOver(results) = &table.status {
+            match overall_winner(results) {
+                Winner::Player => report.player_wins += 1,
+                Winner::Casino => report.casino_wins += 1,
+                Winner::Tie => report.ties += 1,
+            }
+        }
+
+        if round % sample_every == 0 || round == rounds {
+            report.trajectory.push(table.bankrolls[0] as i64 - SIMULATION_BANKROLL as i64);
+        }
+
+        table.clear_round();
+    }
+
+    return report;
+}
+
+/// Carries out `action`, or what stands in for it when the table doesn't
+/// allow a double or another split.
+fn play_action(table: &mut Table, action: Action) {
+    match playable_action(table, action) {
+        Action::Hit => table.hit(),
+        Action::Stand => table.stand(),
+        Action::DoubleDown => table.double_down(),
+        Action::Split => table.split(),
+    }
+}
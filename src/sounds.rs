@@ -0,0 +1,52 @@
+use sdl2::mixer::{self, Channel, Chunk};
+
+const DEAL_SOUND_PATH: &str = "assets/sounds/deal.wav";
+const WIN_SOUND_PATH: &str = "assets/sounds/win.wav";
+const LOSE_SOUND_PATH: &str = "assets/sounds/lose.wav";
+const BUST_SOUND_PATH: &str = "assets/sounds/bust.wav";
+
+#[derive(Clone, Copy)]
+pub enum Sound {
+    Deal,
+    Win,
+    Lose,
+    Bust
+}
+
+/// Sound effect clips, loaded once at startup.
+pub struct Sounds {
+    deal: Chunk,
+    win: Chunk,
+    lose: Chunk,
+    bust: Chunk
+}
+
+impl Sounds {
+    pub fn load() -> Result<Sounds, String> {
+        mixer::open_audio(mixer::DEFAULT_FREQUENCY, mixer::DEFAULT_FORMAT, mixer::DEFAULT_CHANNELS, 1024)
+            .map_err(|err| format!("failed to open audio: {}", err))?;
+
+        return Ok(Sounds {
+            deal: load_chunk(DEAL_SOUND_PATH)?,
+            win: load_chunk(WIN_SOUND_PATH)?,
+            lose: load_chunk(LOSE_SOUND_PATH)?,
+            bust: load_chunk(BUST_SOUND_PATH)?
+        });
+    }
+
+    pub fn play(&self, sound: Sound) {
+        let chunk = match sound {
+            Sound::Deal => &self.deal,
+            Sound::Win => &self.win,
+            Sound::Lose => &self.lose,
+            Sound::Bust => &self.bust,
+        };
+
+        // With every channel busy the clip is simply skipped.
+        let _ = Channel::all().play(chunk, 0);
+    }
+}
+
+fn load_chunk(path: &str) -> Result<Chunk, String> {
+    return Chunk::from_file(path).map_err(|err| format!("failed to load sound {}: {}", path, err));
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::achievements::Achievement;
+use crate::{RoundResult, Winner};
+
+pub const STATS_PATH: &str = "stats.toml";
+
+/// Lifetime record of round outcomes, kept between sessions.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct Stats {
+    pub player_wins: u32,
+    pub casino_wins: u32,
+    pub ties: u32,
+    pub achievements: Vec<Achievement>,
+    /// Hands bet on, counted apart from the rounds as a split plays several.
+    pub hands: u32,
+    pub wagered: u64,
+    /// What the player won back beyond their bets, less what they lost.
+    pub net: i64
+}
+
+impl Stats {
+    /// Reads the stats file at `path`; a missing file starts a fresh record.
+    pub fn load(path: &str) -> Result<Stats, String> {
+        if !Path::new(path).exists() {
+            return Ok(Stats::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read stats {}: {}", path, err))?;
+
+        return toml::from_str(&content)
+            .map_err(|err: toml::de::Error| format!("invalid stats {}: {}", path, err.message()));
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|err| format!("failed to write stats {}: {}", path, err))?;
+
+        return fs::write(path, content)
+            .map_err(|err| format!("failed to write stats {}: {}", path, err));
+    }
+
+    /// Marks `achievement` as unlocked; true if it wasn't already.
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.achievements.contains(&achievement) {
+            return false;
+        }
+
+        self.achievements.push(achievement);
+        return true;
+    }
+
+    /// Adds each hand's bet and what it paid back to the lifetime totals.
+    pub fn record_bets(&mut self, results: &[RoundResult]) {
+        for result in results {
+            self.hands += 1;
+            self.wagered += result.bet as u64;
+            self.net += result.payout as i64 - result.bet as i64;
+        }
+    }
+
+    pub fn rounds(&self) -> u32 {
+        return self.player_wins + self.casino_wins + self.ties;
+    }
+
+    /// Share of rounds the player won, in percent.
+    pub fn win_percentage(&self) -> f64 {
+        if self.rounds() == 0 {
+            return 0.0;
+        }
+
+        return self.player_wins as f64 * 100.0 / self.rounds() as f64;
+    }
+
+    pub fn average_bet(&self) -> f64 {
+        if self.hands == 0 {
+            return 0.0;
+        }
+
+        return self.wagered as f64 / self.hands as f64;
+    }
+
+    /// What the house has kept of every unit wagered, in percent; negative
+    /// while the player is ahead.
+    pub fn house_edge(&self) -> f64 {
+        if self.wagered == 0 {
+            return 0.0;
+        }
+
+        return -self.net as f64 * 100.0 / self.wagered as f64;
+    }
+
+    pub fn record(&mut self, winner: Winner) {
+        match winner {
+            Winner::Player => self.player_wins += 1,
+            Winner::Casino => self.casino_wins += 1,
+            Winner::Tie => self.ties += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RulesConfig;
+    use crate::resolve_round;
+
+    #[test]
+    fn stats_work_out_the_realized_house_edge() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.house_edge(), 0.0);
+
+        stats.record(Winner::Player);
+        stats.record_bets(&[resolve_round(20, 18, false, false, 10, &RulesConfig::default())]);
+        stats.record(Winner::Casino);
+        stats.record_bets(&[
+            resolve_round(17, 19, false, false, 20, &RulesConfig::default()),
+            resolve_round(22, 19, false, false, 20, &RulesConfig::default()),
+        ]);
+
+        assert_eq!(stats.win_percentage(), 50.0);
+        assert_eq!(stats.hands, 3);
+        assert_eq!(stats.average_bet(), 50.0 / 3.0);
+        assert_eq!(stats.net, -30);
+        assert_eq!(stats.house_edge(), 60.0);
+    }
+}
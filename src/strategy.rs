@@ -0,0 +1,88 @@
+use crate::{evaluate_hand, is_pair, Action, CardType, Table};
+
+/// Basic strategy for a multi-deck shoe where the dealer stands on soft 17
+/// and doubling after a split is allowed. Doubling and splitting are only
+/// suggested on the opening two cards; where a double isn't possible the
+/// hand is hit instead, except soft 18 which stands.
+pub fn basic_strategy(player: &[CardType], dealer_upcard: CardType) -> Action {
+    let opening = player.len() == 2;
+
+    return basic_strategy_allowing(player, dealer_upcard, opening, opening);
+}
+
+/// Basic strategy when the hand may only be doubled or split as allowed.
+pub(crate) fn basic_strategy_allowing(player: &[CardType], dealer_upcard: CardType, can_double: bool, can_split: bool) -> Action {
+    let dealer = dealer_upcard.get_score();
+
+    if can_split && is_pair(player) {
+        if let Some(action) = pair_strategy(player[0], dealer) {
+            return action;
+        }
+    }
+
+    let hand = evaluate_hand(player);
+    let (total, soft) = (hand.total, hand.soft);
+    let double_or = |fallback: Action| if can_double { Action::DoubleDown } else { fallback };
+
+    if soft {
+        return match total {
+            13 | 14 if (5..=6).contains(&dealer) => double_or(Action::Hit),
+            15 | 16 if (4..=6).contains(&dealer) => double_or(Action::Hit),
+            17 if (3..=6).contains(&dealer) => double_or(Action::Hit),
+            18 if (3..=6).contains(&dealer) => double_or(Action::Stand),
+            18 if dealer >= 9 => Action::Hit,
+            18..=21 => Action::Stand,
+            _ => Action::Hit,
+        };
+    }
+
+    return match total {
+        9 if (3..=6).contains(&dealer) => double_or(Action::Hit),
+        10 if dealer <= 9 => double_or(Action::Hit),
+        11 if dealer <= 10 => double_or(Action::Hit),
+        12 if (4..=6).contains(&dealer) => Action::Stand,
+        13..=16 if dealer <= 6 => Action::Stand,
+        17..=21 => Action::Stand,
+        _ => Action::Hit,
+    };
+}
+
+/// Pairs worth splitting against `dealer`; the rest are played on their total.
+fn pair_strategy(card: CardType, dealer: usize) -> Option<Action> {
+    let split = match card {
+        CardType::Ace | CardType::Eight => true,
+        CardType::Nine => dealer <= 9 && dealer != 7,
+        CardType::Seven | CardType::Three | CardType::Two => dealer <= 7,
+        CardType::Six => dealer <= 6,
+        CardType::Four => (5..=6).contains(&dealer),
+        _ => false,
+    };
+
+    return if split { Some(Action::Split) } else { None };
+}
+
+/// Basic strategy for the table's active hand against the dealer's up card.
+pub fn suggest(table: &Table) -> Action {
+    let hand = table.player_hands[table.active_hand].cards.card_types();
+    let upcard = table.casino_hand.cards()[0].card_type;
+
+    return basic_strategy(&hand, upcard);
+}
+
+/// `action` if the table allows it, otherwise what basic strategy plays
+/// without the double or split it can't make, or a stand on a hand that
+/// can't be hit.
+pub fn playable_action(table: &Table, action: Action) -> Action {
+    return match action {
+        Action::DoubleDown if table.can_double() => Action::DoubleDown,
+        Action::Split if table.can_split() => Action::Split,
+        Action::Stand => Action::Stand,
+        _ if !table.can_hit() => Action::Stand,
+        Action::Hit => Action::Hit,
+        _ => {
+            let hand = table.player_hands[table.active_hand].cards.card_types();
+            let upcard = table.casino_hand.cards()[0].card_type;
+            basic_strategy_allowing(&hand, upcard, table.can_double(), table.can_split())
+        }
+    };
+}